@@ -0,0 +1,161 @@
+
+use specs::{System, VecStorage, Entities, Fetch, ReadStorage, WriteStorage, World, DispatcherBuilder};
+
+use components::graphics::RenderableSprite;
+use components::positioning::{Animating, Direction};
+use core::animation::{Clip, ClipSet, Motion, PlayMode};
+use core::grid::GridDirection;
+use core::handle::Handle;
+use resources::assets::Assets;
+use resources::delta_time::DeltaTime;
+
+/// Plays the clip matching what an entity is doing and where it faces.
+#[derive(Component, Debug)]
+#[component(VecStorage)]
+pub struct SpriteAnimation {
+    pub clips: Handle<ClipSet>,
+    motion: Motion,
+    direction: GridDirection,
+    // Seconds into the current clip
+    time: f32,
+}
+
+impl SpriteAnimation {
+    pub fn new(clips: Handle<ClipSet>) -> Self {
+        SpriteAnimation {
+            clips: clips,
+            motion: Motion::Idle,
+            direction: GridDirection::DirectionSouth,
+            time: 0.0,
+        }
+    }
+
+    /// Switch clips, a new motion starts its clip over while turning keeps
+    /// the time so walk cycles do not stutter.
+    pub fn play(&mut self, motion: Motion, direction: GridDirection) {
+        if motion != self.motion {
+            self.time = 0.0;
+        }
+        self.motion = motion;
+        self.direction = direction;
+    }
+
+    /// Move the time forward within a clip. Loops wrap around so the time
+    /// stays small however long they play, one-shot clips stop at their end.
+    fn advance(&mut self, delta: f32, clip: &Clip) {
+        let time = self.time + delta;
+        self.time = match clip.mode {
+            PlayMode::Loop => time % clip.duration(),
+            PlayMode::Once => time.min(clip.duration()),
+        };
+    }
+
+    pub fn motion(&self) -> Motion {
+        self.motion
+    }
+
+    pub fn time(&self) -> f32 {
+        self.time
+    }
+}
+
+/// Advances animations and shows their current frame.
+///
+/// Entities walk while they are moving between tiles and idle otherwise.
+pub struct Animate;
+
+impl<'a> System<'a> for Animate {
+    type SystemData = (Entities<'a>,
+     Fetch<'a, DeltaTime>,
+     Fetch<'a, Assets>,
+     ReadStorage<'a, Direction>,
+     ReadStorage<'a, Animating>,
+     WriteStorage<'a, SpriteAnimation>,
+     WriteStorage<'a, RenderableSprite>);
+
+    fn run(&mut self, (entities, delta, assets, direction, animating, mut animation, mut sprite): Self::SystemData) {
+        use specs::Join;
+
+        for (entity, direction, animation, sprite) in (&*entities, &direction, &mut animation, &mut sprite).join() {
+            let motion = match animating.get(entity) {
+                Some(&Animating(true)) => Motion::Walk,
+                _ => Motion::Idle,
+            };
+            animation.play(motion, direction.0);
+
+            if let Some(clip) = assets.clips(animation.clips).clip(animation.motion, animation.direction) {
+                animation.advance(delta.0, clip);
+                sprite.0 = clip.frame_at(animation.time).sprite;
+            }
+        }
+    }
+}
+
+pub fn init_world<'a, 'b>(world: &mut World,
+                          dispatcher_builder: DispatcherBuilder<'a, 'b>)
+                          -> DispatcherBuilder<'a, 'b> {
+    world.register::<SpriteAnimation>();
+
+    dispatcher_builder.add(Animate, "Animate", &["Move"])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use core::animation::Frame;
+
+    fn clip(mode: PlayMode) -> Clip {
+        Clip {
+            mode: mode,
+            frames: vec![Frame {
+                             sprite: Handle::new(0),
+                             duration: 0.25,
+                         },
+                         Frame {
+                             sprite: Handle::new(1),
+                             duration: 0.5,
+                         }],
+        }
+    }
+
+    #[test]
+    fn looping_time_wraps() {
+        let clip = clip(PlayMode::Loop);
+        let mut animation = SpriteAnimation::new(Handle::new(0));
+
+        for _ in 0..1000 {
+            animation.advance(0.125, &clip);
+            assert!(animation.time() < clip.duration());
+        }
+        // 125 seconds in, half a second into the clip
+        assert_eq!(animation.time(), 0.5);
+    }
+
+    #[test]
+    fn one_shot_time_stops_at_the_end() {
+        let clip = clip(PlayMode::Once);
+        let mut animation = SpriteAnimation::new(Handle::new(0));
+
+        animation.advance(0.5, &clip);
+        assert_eq!(animation.time(), 0.5);
+        animation.advance(10.0, &clip);
+        assert_eq!(animation.time(), clip.duration());
+        assert!(clip.is_finished(animation.time()));
+    }
+
+    #[test]
+    fn new_motion_restarts_the_clip() {
+        let clip = clip(PlayMode::Loop);
+        let mut animation = SpriteAnimation::new(Handle::new(0));
+        animation.advance(0.5, &clip);
+
+        // Turning keeps the time
+        animation.play(Motion::Idle, GridDirection::DirectionEast);
+        assert_eq!(animation.time(), 0.5);
+
+        animation.play(Motion::Walk, GridDirection::DirectionEast);
+        assert_eq!(animation.motion(), Motion::Walk);
+        assert_eq!(animation.time(), 0.0);
+    }
+}
@@ -0,0 +1,219 @@
+
+use std::error::Error;
+use std::fmt;
+
+use cgmath::Vector2;
+use specs::{Entity, HashMapStorage, World, DispatcherBuilder};
+
+use resources::occupancy::Occupancy;
+use core::grid::{Board, GridCoordinate, GridDirection};
+
+/// Ground an entity may walk on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Walkability {
+    /// Every tile on the board.
+    AnyTile,
+    /// Only captured, safe ground.
+    SafeOnly,
+    /// Only ground that has not been captured yet.
+    OpenOnly,
+}
+
+/// Movement rules of an entity, entities without one may walk anywhere.
+#[derive(Component, Debug)]
+#[component(HashMapStorage)]
+pub struct Collider {
+    pub walkability: Walkability,
+}
+
+impl Collider {
+    pub fn new(walkability: Walkability) -> Self {
+        Collider { walkability: walkability }
+    }
+}
+
+/// Marks entities other entities can not move through.
+#[derive(Component, Debug)]
+#[component(HashMapStorage)]
+pub struct Solid;
+
+/// Why a move was refused.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MoveRejection {
+    /// The tile is off the board.
+    OutOfBounds,
+    /// The mover is not allowed on that kind of ground.
+    NotWalkable,
+    /// A solid entity stands on, or is moving onto, the tile.
+    Occupied(Entity),
+}
+
+impl fmt::Display for MoveRejection {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+impl Error for MoveRejection {
+    fn description(&self) -> &str {
+        match *self {
+            MoveRejection::OutOfBounds => "tile is outside the board",
+            MoveRejection::NotWalkable => "tile can not be walked on",
+            MoveRejection::Occupied(_) => "tile is occupied",
+        }
+    }
+}
+
+/// Tiles solid entities are moving onto, so two of them do not pick the
+/// same one.
+pub struct Blockers(Vec<(GridCoordinate, Entity)>);
+
+impl Blockers {
+    pub fn new() -> Self {
+        Blockers(Vec::new())
+    }
+
+    /// Collect the world points solid entities are moving to, if any.
+    pub fn from_targets<I>(board: &Board, targets: I) -> Self
+        where I: IntoIterator<Item = (Entity, Option<Vector2<f32>>)>
+    {
+        let mut blockers = Blockers::new();
+        for (entity, target) in targets {
+            if let Some(target) = target {
+                blockers.add(board.get_tile_coordinates(target), entity);
+            }
+        }
+        blockers
+    }
+
+    pub fn add(&mut self, coord: GridCoordinate, entity: Entity) {
+        self.0.push((coord, entity));
+    }
+
+    /// Entity other than the mover heading for a tile.
+    pub fn at(&self, coord: GridCoordinate, mover: Entity) -> Option<Entity> {
+        self.0.iter().find(|&&(c, e)| c == coord && e != mover).map(|&(_, e)| e)
+    }
+}
+
+/// Solid entity other than the mover standing on a tile or moving onto it.
+pub fn occupant<F>(occupancy: &Occupancy,
+                   moving: &Blockers,
+                   coord: GridCoordinate,
+                   mover: Entity,
+                   is_solid: F)
+                   -> Option<Entity>
+    where F: Fn(Entity) -> bool
+{
+    occupancy.find_at(coord, |e| e != mover && is_solid(e)).or_else(|| moving.at(coord, mover))
+}
+
+/// Check the ground of a tile only, ignoring other entities.
+pub fn check_tile(board: &Board, coord: GridCoordinate, walkability: Walkability) -> Result<(), MoveRejection> {
+    if !board.contains(coord) {
+        return Err(MoveRejection::OutOfBounds);
+    }
+
+    let walkable = match walkability {
+        Walkability::AnyTile => true,
+        Walkability::SafeOnly => board.is_free_move(coord),
+        Walkability::OpenOnly => !board.is_free_move(coord),
+    };
+
+    if walkable { Ok(()) } else { Err(MoveRejection::NotWalkable) }
+}
+
+/// Check a one tile move, returning the tile moved onto.
+///
+/// The board bounds are checked first, then the ground and then the solid
+/// entities already there. `occupant` tells which solid entity, other than
+/// the mover, blocks a tile.
+pub fn check_move<F>(board: &Board,
+                     from: GridCoordinate,
+                     direction: GridDirection,
+                     walkability: Walkability,
+                     occupant: F)
+                     -> Result<GridCoordinate, MoveRejection>
+    where F: Fn(GridCoordinate) -> Option<Entity>
+{
+    let next = board.get_neighbour(from, direction).ok_or(MoveRejection::OutOfBounds)?;
+    check_tile(board, next, walkability)?;
+
+    match occupant(next) {
+        Some(entity) => Err(MoveRejection::Occupied(entity)),
+        None => Ok(next),
+    }
+}
+
+pub fn init_world<'a, 'b>(world: &mut World,
+                          dispatcher_builder: DispatcherBuilder<'a, 'b>)
+                          -> DispatcherBuilder<'a, 'b> {
+    world.register::<Collider>();
+    world.register::<Solid>();
+
+    dispatcher_builder
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use core::grid::GridDirection::*;
+
+    /// 4 by 3 board, the two middle tiles are open and the border safe.
+    fn board() -> Board {
+        Board::new(4, 3, 30.0, Vector2::new(0.0, 0.0))
+    }
+
+    fn nobody(_: GridCoordinate) -> Option<Entity> {
+        None
+    }
+
+    #[test]
+    fn moves_onto_walkable_tiles() {
+        let board = board();
+        assert_eq!(check_move(&board, [1, 0], DirectionSouth, Walkability::OpenOnly, nobody), Ok([1, 1]));
+        assert_eq!(check_move(&board, [1, 1], DirectionWest, Walkability::SafeOnly, nobody), Ok([0, 1]));
+        assert_eq!(check_move(&board, [1, 1], DirectionEast, Walkability::AnyTile, nobody), Ok([2, 1]));
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let board = board();
+        let rejected = Err(MoveRejection::OutOfBounds);
+
+        assert_eq!(check_move(&board, [0, 1], DirectionWest, Walkability::AnyTile, nobody), rejected);
+        assert_eq!(check_move(&board, [2, 0], DirectionNorth, Walkability::AnyTile, nobody), rejected);
+        assert_eq!(check_move(&board, [3, 1], DirectionEast, Walkability::AnyTile, nobody), rejected);
+        assert_eq!(check_move(&board, [1, 2], DirectionSouth, Walkability::AnyTile, nobody), rejected);
+        assert_eq!(check_tile(&board, [4, 0], Walkability::AnyTile), Err(MoveRejection::OutOfBounds));
+        assert_eq!(check_tile(&board, [0, 3], Walkability::AnyTile), Err(MoveRejection::OutOfBounds));
+    }
+
+    #[test]
+    fn not_walkable() {
+        let board = board();
+        let rejected = Err(MoveRejection::NotWalkable);
+
+        assert_eq!(check_move(&board, [1, 1], DirectionNorth, Walkability::OpenOnly, nobody), rejected);
+        assert_eq!(check_move(&board, [1, 0], DirectionSouth, Walkability::SafeOnly, nobody), rejected);
+        assert_eq!(check_tile(&board, [2, 1], Walkability::SafeOnly), rejected);
+        assert_eq!(check_tile(&board, [3, 2], Walkability::OpenOnly), rejected);
+    }
+
+    #[test]
+    fn occupied() {
+        let mut world = World::new();
+        let blocker = world.create_entity().build();
+        let board = board();
+
+        let at_center = |coord: GridCoordinate| if coord == [2, 1] { Some(blocker) } else { None };
+        assert_eq!(check_move(&board, [1, 1], DirectionEast, Walkability::AnyTile, &at_center),
+                   Err(MoveRejection::Occupied(blocker)));
+        assert_eq!(check_move(&board, [1, 1], DirectionSouth, Walkability::AnyTile, &at_center), Ok([1, 2]));
+
+        // Ground and bounds are checked before occupants
+        assert_eq!(check_move(&board, [1, 1], DirectionEast, Walkability::SafeOnly, &at_center),
+                   Err(MoveRejection::NotWalkable));
+    }
+}
@@ -0,0 +1,240 @@
+
+use specs::{System, HashMapStorage, Entities, Fetch, FetchMut, ReadStorage, WriteStorage, World,
+            DispatcherBuilder};
+use cgmath::Vector2;
+
+use resources::delta_time;
+use resources::occupancy::Occupancy;
+use components::graphics::GameBoard;
+use components::collision::{self, Blockers, Collider, Solid, Walkability};
+use components::input::Controllable;
+use components::positioning::{Animating, Direction, GridPosition, Position, TargetPosition,
+                              Velocity};
+use core::grid::{self, GridCoordinate};
+use core::pathfinding::{self, FlowField};
+use core::rng::Rng;
+
+/// Seed used for enemy decisions unless a level provides its own.
+pub const DEFAULT_SEED: u64 = 0x5EED;
+
+/// Marks entities that are hostile to the player.
+#[derive(Component, Debug)]
+#[component(HashMapStorage)]
+pub struct Enemy;
+
+/// Enemy moving diagonally through open tiles, reflecting off safe ground.
+#[derive(Component, Debug)]
+#[component(HashMapStorage)]
+pub struct Bouncer;
+
+impl Bouncer {
+    /// Pick one of the four diagonal directions with the given speed.
+    pub fn initial_velocity(rng: &mut Rng, speed: f32) -> Vector2<f32> {
+        let x = if rng.range(2) == 0 { speed } else { -speed };
+        let y = if rng.range(2) == 0 { speed } else { -speed };
+        Vector2::new(x, y)
+    }
+}
+
+/// Enemy walking tile by tile on safe ground, hunting the player.
+#[derive(Component, Debug)]
+#[component(HashMapStorage)]
+pub struct Patroller {
+    // Tile we came from, patrollers only turn back in dead ends
+    previous: Option<GridCoordinate>,
+}
+
+impl Patroller {
+    pub fn new() -> Self {
+        Patroller { previous: None }
+    }
+}
+
+/// Random source for all enemy decisions, seeded so runs are reproducible.
+pub struct EnemyRng(pub Rng);
+
+pub struct Bounce;
+
+impl<'a> System<'a> for Bounce {
+    type SystemData = (Fetch<'a, delta_time::DeltaTime>,
+     Fetch<'a, GameBoard>,
+     ReadStorage<'a, Bouncer>,
+     WriteStorage<'a, Velocity>,
+     WriteStorage<'a, Position>,
+     WriteStorage<'a, GridPosition>);
+
+    fn run(&mut self, (delta, board, bouncer, mut vel, mut pos, mut grid_pos): Self::SystemData) {
+        use specs::Join;
+
+        let delta = delta.0;
+        let board = &board.0;
+
+        // Safe ground and everything outside the board acts as a wall
+        let blocked = |p: Vector2<f32>| match board.tile_at(p) {
+            Some(c) => board.is_free_move(c),
+            None => true,
+        };
+
+        for (_b, vel, pos, gp) in (&bouncer, &mut vel, &mut pos, &mut grid_pos).join() {
+            let step = vel.0 * delta;
+            let next_x = blocked(Vector2::new(pos.0.x + step.x, pos.0.y));
+            let next_y = blocked(Vector2::new(pos.0.x, pos.0.y + step.y));
+
+            if next_x {
+                vel.0.x = -vel.0.x;
+            }
+            if next_y {
+                vel.0.y = -vel.0.y;
+            }
+            if !next_x && !next_y && blocked(pos.0 + step) {
+                // Hit a corner head on
+                vel.0 = -vel.0;
+            }
+
+            let next = pos.0 + vel.0 * delta;
+            if !blocked(next) {
+                pos.0 = next;
+            }
+            gp.0 = board.get_tile_coordinates(pos.0);
+        }
+    }
+}
+
+pub struct Patrol {
+    // Distances to the player over safe ground, kept to reuse its buffers
+    field: FlowField,
+}
+
+impl Patrol {
+    pub fn new() -> Self {
+        Patrol { field: FlowField::new() }
+    }
+}
+
+// TODO: Type very complicated
+impl<'a> System<'a> for Patrol {
+    type SystemData = (Entities<'a>,
+     Fetch<'a, GameBoard>,
+     Fetch<'a, Occupancy>,
+     FetchMut<'a, EnemyRng>,
+     ReadStorage<'a, Controllable>,
+     ReadStorage<'a, Collider>,
+     ReadStorage<'a, Solid>,
+     ReadStorage<'a, GridPosition>,
+     WriteStorage<'a, Patroller>,
+     WriteStorage<'a, Animating>,
+     WriteStorage<'a, TargetPosition>,
+     WriteStorage<'a, Direction>);
+
+    fn run(&mut self,
+           (entities, board, occupancy, mut rng, controllable, collider, solid, grid_pos, mut patroller, mut animating, mut target, mut direction): Self::SystemData) {
+        use specs::Join;
+
+        let board = &board.0;
+        let rng = &mut rng.0;
+
+        let player = (&controllable, &grid_pos).join().map(|(_c, gp)| gp.0).next();
+
+        let field = &mut self.field;
+        if let Some(p) = player {
+            field.build(board, &[p], |c| pathfinding::safe_tiles(board, c));
+        }
+
+        let mut blockers = Blockers::from_targets(board, (&*entities, &solid, &target).join().map(|(e, _s, t)| (e, t.0)));
+
+        for (entity, patrol, gp, is_anim, tar, d) in (&*entities, &mut patroller, &grid_pos, &mut animating, &mut target, &mut direction).join() {
+            if is_anim.0 {
+                continue;
+            }
+
+            let walkability = collider.get(entity).map_or(Walkability::SafeOnly, |c| c.walkability);
+            let mut options: Vec<(grid::GridDirection, GridCoordinate)> = grid::ALL_DIRECTIONS.iter()
+                .filter_map(|&dir| {
+                    let occupied = |c| collision::occupant(&occupancy, &blockers, c, entity, |e| solid.get(e).is_some());
+                    collision::check_move(board, gp.0, dir, walkability, occupied).ok().map(|c| (dir, c))
+                })
+                .collect();
+
+            if options.len() > 1 {
+                options.retain(|&(_, c)| Some(c) != patrol.previous);
+            }
+
+            // Only keep the moves that bring us closest to the player, along
+            // safe ground when the player can be reached that way
+            if let Some(p) = player {
+                let reachable = options.iter().any(|&(_, c)| field.distance(c).is_some());
+                let distance = |c: GridCoordinate| if reachable {
+                    field.distance(c).unwrap_or(u32::max_value())
+                } else {
+                    grid::manhattan_distance(c, p)
+                };
+
+                let best = options.iter().map(|&(_, c)| distance(c)).min();
+                if let Some(best) = best {
+                    options.retain(|&(_, c)| distance(c) == best);
+                }
+            }
+
+            if options.is_empty() {
+                continue;
+            }
+
+            let (dir, next) = options[rng.range(options.len() as u32) as usize];
+            blockers.add(next, entity);
+            patrol.previous = Some(gp.0);
+            d.0 = dir;
+            tar.0 = Some(board.get_tile_center_world_coordinate(next));
+            is_anim.0 = true;
+        }
+    }
+}
+
+pub fn init_world<'a, 'b>(world: &mut World,
+                          dispatcher_builder: DispatcherBuilder<'a, 'b>)
+                          -> DispatcherBuilder<'a, 'b> {
+    world.register::<Enemy>();
+    world.register::<Bouncer>();
+    world.register::<Patroller>();
+
+    world.add_resource(EnemyRng(Rng::new(DEFAULT_SEED)));
+
+    // Patrollers are animated by the regular tile movement
+    dispatcher_builder.add(Bounce, "Bounce", &["StorePrevious"])
+        .add(Patrol::new(), "Patrol", &[])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use core::level::Level;
+    use simulation::Simulation;
+
+    const MEADOW: &str = include_str!("../../resources/levels/01.lvl");
+
+    /// Enemy positions after every step of a run without input.
+    fn run(seed: u64) -> Vec<Vec<Vector2<f32>>> {
+        let mut level = Level::parse(MEADOW).unwrap();
+        level.seed = Some(seed);
+
+        let mut simulation = Simulation::new();
+        let enemies = simulation.load_level(level).enemies;
+
+        let mut trace = Vec::new();
+        for _ in 0..300 {
+            simulation.step(1.0 / 60.0);
+            let positions = simulation.world.read::<Position>();
+            trace.push(enemies.iter().map(|&e| positions.get(e).unwrap().0).collect());
+        }
+        trace
+    }
+
+    #[test]
+    fn same_seed_same_moves() {
+        let first = run(1337);
+        assert_eq!(first, run(1337));
+
+        // The enemies did move, so the comparison means something
+        assert!(first[0] != first[first.len() - 1]);
+    }
+}
@@ -1,21 +1,41 @@
 
-use specs::{VecStorage, World, DispatcherBuilder};
-use ggez::graphics::{Image, Font};
+use specs::{System, VecStorage, Fetch, FetchMut, World, DispatcherBuilder};
+use ggez::graphics::Font;
 
+use components::territory::CapturedTiles;
 use core::grid::Board;
+use core::handle::Handle;
+use core::tileset::TileSet;
+use resources::assets::{Assets, Sprite};
 
 #[derive(Component, Debug)]
 #[component(VecStorage)]
-pub struct RenderableSprite(pub Image);
+pub struct RenderableSprite(pub Handle<Sprite>);
 
 pub struct GameBoard(pub Board);
 
-pub struct RenderableFont(pub Font);
+pub struct RenderableFont(pub Handle<Font>);
+
+/// Gives the tiles captured this tick, and the ones around them, their
+/// sprites from the tile set again.
+pub struct AssignTileSprites;
+
+impl<'a> System<'a> for AssignTileSprites {
+    type SystemData = (Fetch<'a, TileSet>, Fetch<'a, CapturedTiles>, FetchMut<'a, GameBoard>);
+
+    fn run(&mut self, (tile_set, captured, mut board): Self::SystemData) {
+        if !captured.0.is_empty() {
+            tile_set.refresh(&mut board.0, &captured.0);
+        }
+    }
+}
 
 pub fn init_world<'a, 'b>(world: &mut World,
                           dispatcher_builder: DispatcherBuilder<'a, 'b>)
                           -> DispatcherBuilder<'a, 'b> {
+    world.add_resource(Assets::new());
+    world.add_resource(TileSet::new());
     world.register::<RenderableSprite>();
 
-    dispatcher_builder
+    dispatcher_builder.add(AssignTileSprites, "AssignTileSprites", &["Capture"])
 }
@@ -1,33 +1,262 @@
 
 use std::collections::HashMap;
-use ggez::event::Keycode;
+use ggez::event::{Axis, Button, Keycode};
 
-use specs::{System, HashMapStorage, VecStorage, Fetch, ReadStorage, WriteStorage, World,
+use specs::{System, HashMapStorage, VecStorage, Entities, Fetch, ReadStorage, WriteStorage, World,
             DispatcherBuilder};
 
+use cgmath::Vector2;
+
+use resources::delta_time::DeltaTime;
+use resources::occupancy::Occupancy;
 use components::graphics::GameBoard;
-use core::grid;
+use core::bindings::Bindings;
+use core::gesture::{GestureConfig, GestureRecognizer};
+use core::grid::{self, GridCoordinate};
+use components::collision::{self, Blockers, Collider, Solid, Walkability};
 use components::positioning::{Direction, Animating, Position, TargetPosition};
 
-/// Holds all keypresses
-pub struct KeyboardInput(pub HashMap<Keycode, bool>);
+/// Holds all keypresses and the order they happened in
+pub struct KeyboardInput {
+    // Held keys and when they were pressed, later presses compare greater
+    pressed: HashMap<Keycode, u64>,
+    next_press: u64,
+}
 
 impl KeyboardInput {
     /// Create a new input structure
     pub fn new() -> Self {
-        KeyboardInput(HashMap::new())
+        KeyboardInput {
+            pressed: HashMap::new(),
+            next_press: 0,
+        }
+    }
+
+    /// Press or release a key, repeated presses keep their original time
+    pub fn set(&mut self, btn: Keycode, pressed: bool) {
+        if !pressed {
+            self.pressed.remove(&btn);
+        } else if !self.pressed.contains_key(&btn) {
+            self.pressed.insert(btn, self.next_press);
+            self.next_press += 1;
+        }
+    }
+
+    /// Release every key
+    pub fn clear(&mut self) {
+        self.pressed.clear();
     }
 
     /// Check if a specific button is currently pressed
     pub fn is_pressed(&self, btn: Keycode) -> bool {
-        match self.0.get(&btn) {
-            Some(b) => *b,
-            None => false,
+        self.pressed.contains_key(&btn)
+    }
+
+    /// When a held key was pressed, only useful to compare with other keys
+    pub fn press_time(&self, btn: Keycode) -> Option<u64> {
+        self.pressed.get(&btn).cloned()
+    }
+
+    /// Held keys, oldest press first
+    pub fn pressed_keys(&self) -> Vec<Keycode> {
+        let mut keys: Vec<(Keycode, u64)> = self.pressed.iter().map(|(&k, &t)| (k, t)).collect();
+        keys.sort_by_key(|&(_, t)| t);
+        keys.into_iter().map(|(k, _)| k).collect()
+    }
+}
+
+/// Holds the buttons and sticks of every connected controller.
+///
+/// State is kept per controller so an idle pad can not cancel out another,
+/// queries merge all of them.
+pub struct GamepadInput {
+    buttons: HashMap<(i32, Button), bool>,
+    axes: HashMap<(i32, Axis), f32>,
+}
+
+impl GamepadInput {
+    pub fn new() -> Self {
+        GamepadInput {
+            buttons: HashMap::new(),
+            axes: HashMap::new(),
+        }
+    }
+
+    pub fn set_button(&mut self, instance_id: i32, btn: Button, pressed: bool) {
+        self.buttons.insert((instance_id, btn), pressed);
+    }
+
+    /// Store a raw SDL axis value, scaled to [-1, 1].
+    pub fn set_axis(&mut self, instance_id: i32, axis: Axis, value: i16) {
+        let value = (f32::from(value) / 32767.0).max(-1.0).min(1.0);
+        self.set_axis_value(instance_id, axis, value);
+    }
+
+    pub fn set_axis_value(&mut self, instance_id: i32, axis: Axis, value: f32) {
+        self.axes.insert((instance_id, axis), value);
+    }
+
+    /// Forget everything, used when a replay takes over.
+    pub fn clear(&mut self) {
+        self.buttons.clear();
+        self.axes.clear();
+    }
+
+    /// Check if a button is held on any controller
+    pub fn is_pressed(&self, btn: Button) -> bool {
+        self.buttons.iter().any(|(&(_, b), &down)| b == btn && down)
+    }
+
+    /// Axis value furthest from rest over all controllers
+    pub fn axis(&self, axis: Axis) -> f32 {
+        self.axes
+            .iter()
+            .filter(|&(&(_, a), _)| a == axis)
+            .map(|(_, &v)| v)
+            .fold(0.0, |best, v| if v.abs() > best.abs() { v } else { best })
+    }
+
+    /// Buttons held on any controller.
+    pub fn pressed_buttons(&self) -> Vec<Button> {
+        let mut pressed: Vec<Button> = Vec::new();
+        for (&(_, btn), &down) in &self.buttons {
+            if down && !pressed.contains(&btn) {
+                pressed.push(btn);
+            }
+        }
+        pressed
+    }
+
+    /// Merged value of every axis away from rest.
+    pub fn moved_axes(&self) -> Vec<(Axis, f32)> {
+        let mut moved: Vec<(Axis, f32)> = Vec::new();
+        for &(_, axis) in self.axes.keys() {
+            let value = self.axis(axis);
+            if value != 0.0 && !moved.iter().any(|&(a, _)| a == axis) {
+                moved.push((axis, value));
+            }
+        }
+        moved
+    }
+}
+
+/// What a touch asks for.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum TouchCommand {
+    /// A swipe, acting like a key held for one step.
+    Action(InputAction),
+    /// A tap, asking to walk to a tile.
+    Goto(GridCoordinate),
+}
+
+impl TouchCommand {
+    /// Name used in replays, the action name or `goto:<x>:<y>`.
+    pub fn name(&self) -> String {
+        match *self {
+            TouchCommand::Action(action) => action.name().to_string(),
+            TouchCommand::Goto(tile) => format!("goto:{}:{}", tile[0], tile[1]),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<TouchCommand> {
+        if name.starts_with("goto:") {
+            let coords: Vec<&str> = name["goto:".len()..].split(':').collect();
+            if coords.len() != 2 {
+                return None;
+            }
+            match (coords[0].parse(), coords[1].parse()) {
+                (Ok(x), Ok(y)) => Some(TouchCommand::Goto([x, y])),
+                _ => None,
+            }
+        } else {
+            InputAction::from_name(name).map(TouchCommand::Action)
+        }
+    }
+}
+
+/// Commands recognized from touches, or from the mouse standing in for them.
+///
+/// Gestures are queued as they come in and become active for a single
+/// simulation step, like a key tapped for one tick.
+pub struct TouchInput {
+    pub recognizer: GestureRecognizer,
+    pending: Vec<TouchCommand>,
+    active: Vec<TouchCommand>,
+}
+
+impl TouchInput {
+    pub fn new(config: GestureConfig) -> Self {
+        TouchInput {
+            recognizer: GestureRecognizer::new(config),
+            pending: Vec::new(),
+            active: Vec::new(),
         }
     }
+
+    pub fn push(&mut self, command: TouchCommand) {
+        self.pending.push(command);
+    }
+
+    /// Make the oldest queued command the one for the next step.
+    pub fn start_step(&mut self) {
+        self.active.clear();
+        if !self.pending.is_empty() {
+            self.active.push(self.pending.remove(0));
+        }
+    }
+
+    /// Replace the commands of the next step, used when a replay takes over.
+    pub fn set_active(&mut self, commands: Vec<TouchCommand>) {
+        self.pending.clear();
+        self.active = commands;
+    }
+
+    pub fn active(&self) -> &[TouchCommand] {
+        &self.active
+    }
+
+    /// Check if a swipe asks for an action this step.
+    pub fn is_active(&self, action: InputAction) -> bool {
+        self.active.contains(&TouchCommand::Action(action))
+    }
+
+    /// Tile a tap asks to walk to this step.
+    pub fn goto(&self) -> Option<GridCoordinate> {
+        self.active.iter().filter_map(|c| match *c {
+            TouchCommand::Goto(tile) => Some(tile),
+            _ => None,
+        }).last()
+    }
+}
+
+/// Action for a swipe given in screen space.
+///
+/// On the isometric board every grid direction runs diagonally across the
+/// screen, so each screen quadrant stands for one of them.
+pub fn swipe_action(offset: Vector2<f32>) -> InputAction {
+    match (offset.x >= 0.0, offset.y < 0.0) {
+        (true, true) => InputAction::MoveUp,
+        (true, false) => InputAction::MoveRight,
+        (false, false) => InputAction::MoveDown,
+        (false, true) => InputAction::MoveLeft,
+    }
 }
 
-#[derive(PartialEq, Eq, Hash, Debug)]
+/// Action taking one step from a tile towards another, along the longer axis.
+pub fn action_towards(from: GridCoordinate, to: GridCoordinate) -> Option<InputAction> {
+    let dx = i64::from(to[0]) - i64::from(from[0]);
+    let dy = i64::from(to[1]) - i64::from(from[1]);
+
+    if dx == 0 && dy == 0 {
+        None
+    } else if dx.abs() >= dy.abs() {
+        Some(if dx > 0 { InputAction::MoveRight } else { InputAction::MoveLeft })
+    } else {
+        Some(if dy > 0 { InputAction::MoveDown } else { InputAction::MoveUp })
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
 pub enum InputAction {
     MoveUp,
     MoveDown,
@@ -35,20 +264,209 @@ pub enum InputAction {
     MoveRight,
 }
 
+impl InputAction {
+    /// Name used in replays and configuration files.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            InputAction::MoveUp => "up",
+            InputAction::MoveDown => "down",
+            InputAction::MoveLeft => "left",
+            InputAction::MoveRight => "right",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<InputAction> {
+        ALL_ACTIONS.iter().cloned().find(|a| a.name() == name)
+    }
+
+    /// Grid direction the action moves in.
+    pub fn direction(&self) -> grid::GridDirection {
+        match *self {
+            InputAction::MoveUp => grid::GridDirection::DirectionNorth,
+            InputAction::MoveDown => grid::GridDirection::DirectionSouth,
+            InputAction::MoveLeft => grid::GridDirection::DirectionWest,
+            InputAction::MoveRight => grid::GridDirection::DirectionEast,
+        }
+    }
+
+    pub fn opposite(&self) -> InputAction {
+        match *self {
+            InputAction::MoveUp => InputAction::MoveDown,
+            InputAction::MoveDown => InputAction::MoveUp,
+            InputAction::MoveLeft => InputAction::MoveRight,
+            InputAction::MoveRight => InputAction::MoveLeft,
+        }
+    }
+}
+
+/// Picks the single direction to move in when several are held.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum DirectionPolicy {
+    /// The direction pressed last wins.
+    MostRecent,
+    /// The direction held the longest wins.
+    FirstPressed,
+    /// Opposite directions cancel each other out, the most recent of the
+    /// rest wins.
+    CancelOpposite,
+}
+
+impl DirectionPolicy {
+    /// Name used in configuration files.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            DirectionPolicy::MostRecent => "most_recent",
+            DirectionPolicy::FirstPressed => "first_pressed",
+            DirectionPolicy::CancelOpposite => "cancel_opposite",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<DirectionPolicy> {
+        [DirectionPolicy::MostRecent, DirectionPolicy::FirstPressed, DirectionPolicy::CancelOpposite]
+            .iter()
+            .cloned()
+            .find(|p| p.name() == name)
+    }
+}
+
+/// Every action, in the order conflicts are resolved.
+pub const ALL_ACTIONS: [InputAction; 4] = [InputAction::MoveUp,
+                                           InputAction::MoveDown,
+                                           InputAction::MoveLeft,
+                                           InputAction::MoveRight];
+
+/// Which half of an analog axis a binding listens to.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum AxisDirection {
+    Positive,
+    Negative,
+}
+
+/// Something on an input device that can trigger an action.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum InputBinding {
+    Key(Keycode),
+    Button(Button),
+    Axis(Axis, AxisDirection),
+}
+
+impl InputBinding {
+    /// Check if the binding is held, sticks count once past the dead zone
+    pub fn is_active(&self, keyboard: &KeyboardInput, gamepad: &GamepadInput, dead_zone: f32) -> bool {
+        match *self {
+            InputBinding::Key(code) => keyboard.is_pressed(code),
+            InputBinding::Button(btn) => gamepad.is_pressed(btn),
+            InputBinding::Axis(axis, AxisDirection::Positive) => gamepad.axis(axis) > dead_zone,
+            InputBinding::Axis(axis, AxisDirection::Negative) => gamepad.axis(axis) < -dead_zone,
+        }
+    }
+}
+
 #[derive(Component, Debug)]
 #[component(VecStorage)]
-pub struct InputMapping(pub HashMap<InputAction, Keycode>);
+pub struct InputMapping {
+    pub bindings: HashMap<InputAction, Vec<InputBinding>>,
+    pub dead_zone: f32,
+    pub policy: DirectionPolicy,
+}
 
 impl InputMapping {
-    pub fn default() -> Self {
-        let mut map = HashMap::new();
+    /// Mapping with everything the user has bound.
+    pub fn from_bindings(bindings: &Bindings) -> Self {
+        let map = ALL_ACTIONS.iter().map(|&a| (a, bindings.get(a).to_vec())).collect();
+
+        InputMapping {
+            bindings: map,
+            dead_zone: bindings.dead_zone,
+            policy: bindings.policy,
+        }
+    }
+
+    /// Latest press of a key bound to an action, other devices keep no
+    /// press times.
+    pub fn press_time(&self, action: InputAction, keyboard: &KeyboardInput) -> Option<u64> {
+        let bindings = match self.bindings.get(&action) {
+            Some(bindings) => bindings,
+            None => return None,
+        };
+
+        bindings.iter()
+            .filter_map(|b| match *b {
+                InputBinding::Key(code) => keyboard.press_time(code),
+                _ => None,
+            })
+            .max()
+    }
+
+    /// Check if any binding of an action is held
+    pub fn is_active(&self, action: InputAction, keyboard: &KeyboardInput, gamepad: &GamepadInput) -> bool {
+        match self.bindings.get(&action) {
+            Some(bindings) => bindings.iter().any(|b| b.is_active(keyboard, gamepad, self.dead_zone)),
+            None => false,
+        }
+    }
+}
+
+/// Actions an entity requests this tick, whatever device they came from,
+/// in the order they started.
+#[derive(Component, Debug)]
+#[component(VecStorage)]
+pub struct Actions(pub Vec<InputAction>);
+
+impl Actions {
+    pub fn is_active(&self, action: InputAction) -> bool {
+        self.0.contains(&action)
+    }
+
+    /// The one direction to move in according to a policy.
+    pub fn direction(&self, policy: DirectionPolicy) -> Option<InputAction> {
+        match policy {
+            DirectionPolicy::MostRecent => self.0.last().cloned(),
+            DirectionPolicy::FirstPressed => self.0.first().cloned(),
+            DirectionPolicy::CancelOpposite => {
+                self.0.iter().rev().cloned().find(|a| !self.is_active(a.opposite()))
+            }
+        }
+    }
+}
+
+/// Turns device state into actions for every entity with an input mapping.
+pub struct MapActions;
+
+impl<'a> System<'a> for MapActions {
+    type SystemData = (Entities<'a>,
+     Fetch<'a, KeyboardInput>,
+     Fetch<'a, GamepadInput>,
+     Fetch<'a, TouchInput>,
+     ReadStorage<'a, InputMapping>,
+     WriteStorage<'a, Actions>);
 
-        map.insert(InputAction::MoveUp, Keycode::W);
-        map.insert(InputAction::MoveDown, Keycode::S);
-        map.insert(InputAction::MoveLeft, Keycode::A);
-        map.insert(InputAction::MoveRight, Keycode::D);
+    fn run(&mut self, (entities, keyboard, gamepad, touch, mapping, mut actions): Self::SystemData) {
+        use specs::Join;
+
+        for (entity, mapping) in (&*entities, &mapping).join() {
+            let mut held: Vec<InputAction> = ALL_ACTIONS.iter()
+                .cloned()
+                .filter(|&a| mapping.is_active(a, &keyboard, &gamepad) || touch.is_active(a))
+                .collect();
 
-        InputMapping(map)
+            // Actions started this tick go in key press order, those without
+            // a key press after them
+            held.sort_by_key(|&a| mapping.press_time(a, &keyboard).unwrap_or(u64::max_value()));
+
+            // Actions still held keep their place
+            let mut ordered: Vec<InputAction> = match actions.get(entity) {
+                Some(previous) => previous.0.iter().cloned().filter(|a| held.contains(a)).collect(),
+                None => Vec::new(),
+            };
+            for action in held {
+                if !ordered.contains(&action) {
+                    ordered.push(action);
+                }
+            }
+
+            actions.insert(entity, Actions(ordered));
+        }
     }
 }
 
@@ -56,77 +474,102 @@ impl InputMapping {
 #[component(HashMapStorage)]
 pub struct Controllable;
 
+/// How long, in seconds, a direction pressed during a move is remembered.
+pub const DEFAULT_BUFFER_EXPIRY: f32 = 0.2;
+
+/// Remembers the last direction asked for while the entity was busy moving,
+/// so a turn pressed just before reaching a tile is not lost.
+#[derive(Component, Debug)]
+#[component(HashMapStorage)]
+pub struct InputBuffer {
+    direction: Option<grid::GridDirection>,
+    age: f32,
+    /// Seconds a buffered direction stays valid.
+    pub expiry: f32,
+}
+
+impl InputBuffer {
+    pub fn new(expiry: f32) -> Self {
+        InputBuffer {
+            direction: None,
+            age: 0.0,
+            expiry: expiry,
+        }
+    }
+
+    pub fn push(&mut self, direction: grid::GridDirection) {
+        self.direction = Some(direction);
+        self.age = 0.0;
+    }
+
+    /// Age the buffer, dropping the direction once it expires.
+    pub fn tick(&mut self, delta: f32) {
+        self.age += delta;
+        if self.age > self.expiry {
+            self.direction = None;
+        }
+    }
+
+    pub fn take(&mut self) -> Option<grid::GridDirection> {
+        self.direction.take()
+    }
+}
+
 pub struct Control;
 
 impl<'a> System<'a> for Control {
-    type SystemData = (Fetch<'a, KeyboardInput>,
+    type SystemData = (Entities<'a>,
+     Fetch<'a, DeltaTime>,
      Fetch<'a, GameBoard>,
+     Fetch<'a, Occupancy>,
+     ReadStorage<'a, Actions>,
      ReadStorage<'a, InputMapping>,
      ReadStorage<'a, Controllable>,
+     ReadStorage<'a, Collider>,
+     ReadStorage<'a, Solid>,
      ReadStorage<'a, Position>,
+     WriteStorage<'a, InputBuffer>,
      WriteStorage<'a, Animating>,
      WriteStorage<'a, TargetPosition>,
      WriteStorage<'a, Direction>);
 
     fn run(&mut self,
-           (keyboard_input, board, input_mapping, controllable, position, mut animating, mut target, mut direction): Self::SystemData) {
+           (entities, delta, board, occupancy, actions, mapping, controllable, collider, solid, position, mut buffer, mut animating, mut target, mut direction): Self::SystemData) {
         use specs::Join;
 
-        let keyboard_input = &*keyboard_input;
         let board = &board.0;
 
-        // TODO: Direction should probably be exclusive in that sense that,
-        //       we can only go up, only down, only left or only right.
-        //
-        //       Diagonal movement should be invalid.
-        let mut dir = grid::GridDirection::DirectionNorth;
-        let mut btn_update = false;
-
-        for mapping in input_mapping.join() {
-
-            for (action, &code) in &mapping.0 {
-                match *action {
-                    InputAction::MoveUp => {
-                        if keyboard_input.is_pressed(code) {
-                            dir = grid::GridDirection::DirectionNorth;
-                            btn_update = true;
-                        }
-                    }
-                    InputAction::MoveDown => {
-                        if keyboard_input.is_pressed(code) {
-                            dir = grid::GridDirection::DirectionSouth;
-                            btn_update = true;
-                        }
-                    }
-                    InputAction::MoveRight => {
-                        if keyboard_input.is_pressed(code) {
-                            dir = grid::GridDirection::DirectionEast;
-                            btn_update = true;
-                        }
-                    }
-                    InputAction::MoveLeft => {
-                        if keyboard_input.is_pressed(code) {
-                            dir = grid::GridDirection::DirectionWest;
-                            btn_update = true;
-                        }
-                    }
+        let blockers = Blockers::from_targets(board, (&*entities, &solid, &target).join().map(|(e, _s, t)| (e, t.0)));
+
+        for (entity, actions, mapping, _c, pos, buf, is_anim, d, tar) in (&*entities, &actions, &mapping, &controllable, &position, &mut buffer, &mut animating, &mut direction, &mut target).join() {
+            // Diagonal movement is invalid, the mapping picks one direction
+            let requested = actions.direction(mapping.policy).map(|a| a.direction());
+
+            buf.tick(delta.0);
+
+            if is_anim.0 {
+                if let Some(dir) = requested {
+                    buf.push(dir);
                 }
+                continue;
             }
-        }
 
-        for (_c, pos, is_anim, d, tar) in (&controllable, &position, &mut animating, &mut direction, &mut target).join() {
-            if !is_anim.0 && btn_update {
+            // Held input wins over something pressed a moment ago
+            let buffered = buf.take();
+            if let Some(dir) = requested.or(buffered) {
                 d.0 = dir;
 
+                let walkability = collider.get(entity).map_or(Walkability::AnyTile, |c| c.walkability);
                 let cur_tile = board.get_tile_coordinates(pos.0);
-                let next_tile = board.get_neighbour(cur_tile, dir);
+                let occupied = |c| collision::occupant(&occupancy, &blockers, c, entity, |e| solid.get(e).is_some());
+                let next_tile = collision::check_move(board, cur_tile, dir, walkability, occupied);
 
                 match next_tile {
-                    Some(c) => {
+                    Ok(c) => {
                         tar.0 = Some(board.get_tile_center_world_coordinate(c));
                         is_anim.0 = true ;
                     },
-                    None => is_anim.0 = false,
+                    Err(_) => is_anim.0 = false,
                 }
             }
         }
@@ -140,7 +583,199 @@ pub fn init_world<'a, 'b>(world: &mut World,
 
     // Create the resource so we can add input to the map.
     world.add_resource(KeyboardInput::new());
+    world.add_resource(GamepadInput::new());
+    world.add_resource(TouchInput::new(GestureConfig::default()));
+    world.add_resource(Bindings::default());
     world.register::<InputMapping>();
+    world.register::<Actions>();
+    world.register::<InputBuffer>();
+
+    dispatcher_builder.add(MapActions, "MapActions", &[])
+        .add(Control, "Control", &["MapActions"])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use specs::Entity;
+
+    use components::positioning::GridPosition;
+    use core::level::Level;
+    use simulation::Simulation;
+
+    /// World with only what `MapActions` reads, and one entity using the
+    /// default bindings with a policy.
+    fn mapped_world(policy: DirectionPolicy) -> (World, Entity) {
+        let mut world = World::new();
+        world.add_resource(KeyboardInput::new());
+        world.add_resource(GamepadInput::new());
+        world.add_resource(TouchInput::new(GestureConfig::default()));
+        world.register::<InputMapping>();
+        world.register::<Actions>();
+
+        let mut bindings = Bindings::default();
+        bindings.policy = policy;
+        let entity = world.create_entity().with(InputMapping::from_bindings(&bindings)).build();
+        (world, entity)
+    }
+
+    /// Press or release keys one tick apart and return the direction the
+    /// policy picks afterwards.
+    fn direction_after(policy: DirectionPolicy, keys: &[(Keycode, bool)]) -> Option<InputAction> {
+        let (mut world, entity) = mapped_world(policy);
+        let mut dispatcher = DispatcherBuilder::new().add(MapActions, "MapActions", &[]).build();
+
+        for &(key, pressed) in keys {
+            world.write_resource::<KeyboardInput>().set(key, pressed);
+            dispatcher.dispatch(&mut world.res);
+        }
+
+        let actions = world.read::<Actions>();
+        let mapping = world.read::<InputMapping>();
+        actions.get(entity).unwrap().direction(mapping.get(entity).unwrap().policy)
+    }
+
+    #[test]
+    fn most_recent_press_wins() {
+        let policy = DirectionPolicy::MostRecent;
+        assert_eq!(direction_after(policy, &[(Keycode::A, true), (Keycode::D, true)]),
+                   Some(InputAction::MoveRight));
+        assert_eq!(direction_after(policy, &[(Keycode::A, true), (Keycode::D, true), (Keycode::W, true)]),
+                   Some(InputAction::MoveUp));
+        assert_eq!(direction_after(policy, &[(Keycode::A, true), (Keycode::D, true), (Keycode::D, false)]),
+                   Some(InputAction::MoveLeft));
+    }
+
+    #[test]
+    fn first_press_wins() {
+        let policy = DirectionPolicy::FirstPressed;
+        assert_eq!(direction_after(policy, &[(Keycode::A, true), (Keycode::D, true)]),
+                   Some(InputAction::MoveLeft));
+        assert_eq!(direction_after(policy, &[(Keycode::A, true), (Keycode::D, true), (Keycode::W, true)]),
+                   Some(InputAction::MoveLeft));
+        assert_eq!(direction_after(policy, &[(Keycode::A, true), (Keycode::D, true), (Keycode::A, false)]),
+                   Some(InputAction::MoveRight));
+    }
+
+    #[test]
+    fn opposites_cancel_out() {
+        let policy = DirectionPolicy::CancelOpposite;
+        assert_eq!(direction_after(policy, &[(Keycode::A, true), (Keycode::D, true)]), None);
+
+        // A third direction is not cancelled, whenever it was pressed
+        assert_eq!(direction_after(policy, &[(Keycode::A, true), (Keycode::D, true), (Keycode::W, true)]),
+                   Some(InputAction::MoveUp));
+        assert_eq!(direction_after(policy, &[(Keycode::W, true), (Keycode::A, true), (Keycode::D, true)]),
+                   Some(InputAction::MoveUp));
+
+        // Releasing one of the opposites lets the other through again
+        assert_eq!(direction_after(policy,
+                                   &[(Keycode::A, true),
+                                     (Keycode::D, true),
+                                     (Keycode::W, true),
+                                     (Keycode::W, false),
+                                     (Keycode::D, false)]),
+                   Some(InputAction::MoveLeft));
+    }
+
+    const STEP: f32 = 1.0 / 60.0;
+
+    /// Player walking right from [1, 1] on an open field, one tick into its
+    /// first step.
+    fn walking_player(expiry: f32) -> (Simulation, Entity) {
+        let level = Level::parse("player = 1 1\n\n[tiles]\n######\n#....#\n#....#\n#....#\n######\n").unwrap();
+        let mut simulation = Simulation::new();
+        let player = simulation.load_level(level).player;
+        simulation.world.write::<InputBuffer>().get_mut(player).unwrap().expiry = expiry;
+
+        simulation.set_key(Keycode::D, true);
+        simulation.step(STEP);
+        simulation.set_key(Keycode::D, false);
+        assert!(is_moving(&simulation, player));
+        (simulation, player)
+    }
 
-    dispatcher_builder.add(Control, "Control", &[])
+    fn is_moving(simulation: &Simulation, player: Entity) -> bool {
+        simulation.world.read::<Animating>().get(player).unwrap().0
+    }
+
+    fn grid_position(simulation: &Simulation, player: Entity) -> GridCoordinate {
+        simulation.world.read::<GridPosition>().get(player).unwrap().0
+    }
+
+    /// Tap a key for one tick.
+    fn tap(simulation: &mut Simulation, key: Keycode) {
+        simulation.set_key(key, true);
+        simulation.step(STEP);
+        simulation.set_key(key, false);
+    }
+
+    /// Step until the current move is done.
+    fn finish_move(simulation: &mut Simulation, player: Entity) {
+        for _ in 0..60 {
+            if !is_moving(simulation, player) {
+                return;
+            }
+            simulation.step(STEP);
+        }
+        panic!("the move never finished");
+    }
+
+    #[test]
+    fn turn_pressed_during_move_is_taken_on_arrival() {
+        let (mut simulation, player) = walking_player(DEFAULT_BUFFER_EXPIRY);
+
+        simulation.step(STEP);
+        tap(&mut simulation, Keycode::S);
+        finish_move(&mut simulation, player);
+        assert_eq!(grid_position(&simulation, player), [2, 1]);
+
+        // The buffered turn starts the next step without any key held
+        for _ in 0..3 {
+            if is_moving(&simulation, player) {
+                break;
+            }
+            simulation.step(STEP);
+        }
+        let target = simulation.world.read::<TargetPosition>().get(player).unwrap().0;
+        let expected = simulation.world.read_resource::<GameBoard>().0.get_tile_center_world_coordinate([2, 2]);
+        assert_eq!(target, Some(expected));
+
+        finish_move(&mut simulation, player);
+        assert_eq!(grid_position(&simulation, player), [2, 2]);
+    }
+
+    #[test]
+    fn turn_pressed_too_early_expires() {
+        // The step takes far longer than the buffer remembers
+        let (mut simulation, player) = walking_player(STEP * 2.0);
+
+        tap(&mut simulation, Keycode::S);
+        finish_move(&mut simulation, player);
+        assert_eq!(grid_position(&simulation, player), [2, 1]);
+
+        for _ in 0..30 {
+            simulation.step(STEP);
+        }
+        assert!(!is_moving(&simulation, player));
+        assert_eq!(grid_position(&simulation, player), [2, 1]);
+    }
+
+    #[test]
+    fn swipe_quadrants_map_to_grid_directions() {
+        // Screen y grows downwards
+        assert_eq!(swipe_action(Vector2::new(30.0, -20.0)), InputAction::MoveUp);
+        assert_eq!(swipe_action(Vector2::new(30.0, 20.0)), InputAction::MoveRight);
+        assert_eq!(swipe_action(Vector2::new(-30.0, 20.0)), InputAction::MoveDown);
+        assert_eq!(swipe_action(Vector2::new(-30.0, -20.0)), InputAction::MoveLeft);
+    }
+
+    #[test]
+    fn swipe_quadrant_edges() {
+        assert_eq!(swipe_action(Vector2::new(0.0, -50.0)), InputAction::MoveUp);
+        assert_eq!(swipe_action(Vector2::new(50.0, 0.0)), InputAction::MoveRight);
+        assert_eq!(swipe_action(Vector2::new(0.0, 50.0)), InputAction::MoveRight);
+        assert_eq!(swipe_action(Vector2::new(-50.0, 0.0)), InputAction::MoveDown);
+    }
 }
@@ -1,4 +1,9 @@
+pub mod animation;
 
+pub mod collision;
+pub mod enemy;
 pub mod input;
+pub mod navigation;
 pub mod graphics;
 pub mod positioning;
+pub mod territory;
@@ -0,0 +1,156 @@
+
+use std::collections::VecDeque;
+
+use specs::{System, HashMapStorage, Entities, Fetch, ReadStorage, WriteStorage, World, DispatcherBuilder};
+
+use components::collision::{self, Blockers, Collider, Solid, Walkability};
+use components::graphics::GameBoard;
+use components::input::{self, Actions, Controllable, TouchInput};
+use components::positioning::{Animating, Direction, GridPosition, TargetPosition};
+use resources::occupancy::Occupancy;
+use core::grid::{self, GridCoordinate};
+use core::pathfinding::PathFinder;
+
+/// Tiles left to walk through, one move at a time.
+#[derive(Component, Debug)]
+#[component(HashMapStorage)]
+pub struct PathFollow {
+    pub waypoints: VecDeque<GridCoordinate>,
+}
+
+impl PathFollow {
+    pub fn new() -> Self {
+        PathFollow { waypoints: VecDeque::new() }
+    }
+
+    pub fn clear(&mut self) {
+        self.waypoints.clear();
+    }
+}
+
+/// Plans a route to the tile the player tapped.
+pub struct PlanPath {
+    finder: PathFinder,
+    // Reused between searches
+    path: Vec<GridCoordinate>,
+}
+
+impl PlanPath {
+    pub fn new() -> Self {
+        PlanPath {
+            finder: PathFinder::new(),
+            path: Vec::new(),
+        }
+    }
+}
+
+impl<'a> System<'a> for PlanPath {
+    type SystemData = (Entities<'a>,
+     Fetch<'a, GameBoard>,
+     Fetch<'a, TouchInput>,
+     ReadStorage<'a, Controllable>,
+     ReadStorage<'a, Collider>,
+     ReadStorage<'a, GridPosition>,
+     ReadStorage<'a, TargetPosition>,
+     WriteStorage<'a, PathFollow>);
+
+    fn run(&mut self, (entities, board, touch, controllable, collider, grid_pos, target, mut follow): Self::SystemData) {
+        use specs::Join;
+
+        let board = &board.0;
+        let goal = match touch.goto() {
+            Some(goal) => goal,
+            None => return,
+        };
+
+        for (entity, _c, gp, tar, follow) in (&*entities, &controllable, &grid_pos, &target, &mut follow).join() {
+            // A move in progress is finished first, plan from where it ends
+            let start = match tar.0 {
+                Some(t) => board.get_tile_coordinates(t),
+                None => gp.0,
+            };
+
+            let walkability = collider.get(entity).map_or(Walkability::AnyTile, |c| c.walkability);
+            self.finder.find_path(board,
+                                  start,
+                                  goal,
+                                  |c| collision::check_tile(board, c, walkability).ok().map(|_| 1),
+                                  &mut self.path);
+            follow.waypoints = self.path.iter().cloned().collect();
+        }
+    }
+}
+
+/// Hands the next waypoint to `Move` whenever the last one is reached.
+pub struct FollowPath;
+
+impl<'a> System<'a> for FollowPath {
+    type SystemData = (Entities<'a>,
+     Fetch<'a, GameBoard>,
+     Fetch<'a, Occupancy>,
+     ReadStorage<'a, Actions>,
+     ReadStorage<'a, Collider>,
+     ReadStorage<'a, Solid>,
+     ReadStorage<'a, GridPosition>,
+     WriteStorage<'a, PathFollow>,
+     WriteStorage<'a, Animating>,
+     WriteStorage<'a, TargetPosition>,
+     WriteStorage<'a, Direction>);
+
+    fn run(&mut self,
+           (entities, board, occupancy, actions, collider, solid, grid_pos, mut follow, mut animating, mut target, mut direction): Self::SystemData) {
+        use specs::Join;
+
+        let board = &board.0;
+
+        let blockers = Blockers::from_targets(board, (&*entities, &solid, &target).join().map(|(e, _s, t)| (e, t.0)));
+
+        for (entity, actions, gp, follow, is_anim, tar, d) in (&*entities, &actions, &grid_pos, &mut follow, &mut animating, &mut target, &mut direction).join() {
+            // Steering by hand takes over from the route
+            if !actions.0.is_empty() {
+                follow.clear();
+                continue;
+            }
+
+            if is_anim.0 {
+                continue;
+            }
+
+            let next = match follow.waypoints.pop_front() {
+                Some(next) => next,
+                None => continue,
+            };
+
+            // Waypoints are next to each other, anything else means the route
+            // no longer starts where we are
+            let action = match input::action_towards(gp.0, next) {
+                Some(action) if grid::manhattan_distance(gp.0, next) == 1 => action,
+                _ => {
+                    follow.clear();
+                    continue;
+                }
+            };
+
+            // Give up on the route when something moved into the way
+            let walkability = collider.get(entity).map_or(Walkability::AnyTile, |c| c.walkability);
+            let occupied = |c| collision::occupant(&occupancy, &blockers, c, entity, |e| solid.get(e).is_some());
+            match collision::check_move(board, gp.0, action.direction(), walkability, occupied) {
+                Ok(next) => {
+                    d.0 = action.direction();
+                    tar.0 = Some(board.get_tile_center_world_coordinate(next));
+                    is_anim.0 = true;
+                }
+                Err(_) => follow.clear(),
+            }
+        }
+    }
+}
+
+pub fn init_world<'a, 'b>(world: &mut World,
+                          dispatcher_builder: DispatcherBuilder<'a, 'b>)
+                          -> DispatcherBuilder<'a, 'b> {
+    world.register::<PathFollow>();
+
+    dispatcher_builder.add(PlanPath::new(), "PlanPath", &[])
+        .add(FollowPath, "FollowPath", &["PlanPath", "Control"])
+}
@@ -1,16 +1,22 @@
 
-use specs::{System, VecStorage, HashMapStorage, Fetch, ReadStorage, WriteStorage, World,
-            DispatcherBuilder};
+use specs::{System, VecStorage, Entities, Fetch, ReadStorage, WriteStorage,
+            World, DispatcherBuilder};
 use cgmath::Vector2;
 
 use resources::delta_time;
 use components::graphics::GameBoard;
-use core::grid::{GridDirection, GridCoordinate};
+use core::grid::{Board, GridDirection, GridCoordinate};
+use core::tween::{Easing, Tween};
 
 #[derive(Component, Debug)]
 #[component(VecStorage)]
 pub struct Position(pub Vector2<f32>);
 
+/// Position at the start of the current tick, used to interpolate rendering.
+#[derive(Component, Debug)]
+#[component(VecStorage)]
+pub struct PreviousPosition(pub Vector2<f32>);
+
 #[derive(Component, Debug)]
 #[component(VecStorage)]
 pub struct TargetPosition(pub Option<Vector2<f32>>);
@@ -31,63 +37,115 @@ pub struct Direction(pub GridDirection);
 #[component(VecStorage)]
 pub struct Animating(pub bool);
 
+/// How an entity steps from one tile to the next.
 #[derive(Component, Debug)]
 #[component(VecStorage)]
-pub struct AnimationTime(pub f32);
+pub struct StepMotion {
+    /// Seconds a step takes.
+    pub duration: f32,
+    pub easing: Easing,
+}
+
+impl StepMotion {
+    pub fn new(duration: f32, easing: Easing) -> Self {
+        StepMotion {
+            duration: duration,
+            easing: easing,
+        }
+    }
+}
+
+pub struct StorePrevious;
+
+impl<'a> System<'a> for StorePrevious {
+    type SystemData = (Entities<'a>, ReadStorage<'a, Position>, WriteStorage<'a, PreviousPosition>);
+
+    fn run(&mut self, (entities, pos, mut prev): Self::SystemData) {
+        use specs::Join;
 
+        for (entity, pos) in (&*entities, &pos).join() {
+            prev.insert(entity, PreviousPosition(pos.0));
+        }
+    }
+}
+
+/// Steps entities towards their target tile along their easing curve, then
+/// puts them exactly on the tile center.
 pub struct Move;
 
 // TODO: Type very complicated
 impl<'a> System<'a> for Move {
-    type SystemData = (Fetch<'a, delta_time::DeltaTime>,
+    type SystemData = (Entities<'a>,
+     Fetch<'a, delta_time::DeltaTime>,
      Fetch<'a, GameBoard>,
-     ReadStorage<'a, Velocity>,
+     ReadStorage<'a, StepMotion>,
      WriteStorage<'a, Animating>,
-     WriteStorage<'a, AnimationTime>,
+     WriteStorage<'a, Tween<Vector2<f32>>>,
      WriteStorage<'a, GridPosition>,
      WriteStorage<'a, TargetPosition>,
      WriteStorage<'a, Position>);
 
-    fn run(&mut self, (delta, board, vel, mut ani, mut ani_time, mut grid_pos, mut tar_pos, mut pos): Self::SystemData) {
+    fn run(&mut self,
+           (entities, delta, board, motion, mut ani, mut tweens, mut grid_pos, mut tar_pos, mut pos): Self::SystemData) {
         use specs::Join;
 
         let delta = delta.0;
         let board = &board.0;
 
-        for (vel, is_anim, ani_time, gp, tp, pos) in (&vel, &mut ani, &mut ani_time, &mut grid_pos, &mut tar_pos, &mut pos).join() {
-            if is_anim.0 {
-                ani_time.0 += vel.0.x * delta;
-
-                let starting_coords = board.get_tile_center_world_coordinate(gp.0);
-                pos.0.x = (1.0 - ani_time.0) * starting_coords.x + ani_time.0 * tp.0.unwrap().x;
-                pos.0.y = (1.0 - ani_time.0) * starting_coords.y + ani_time.0 * tp.0.unwrap().y;
-
-                if ani_time.0 > 1.0 {
-                    is_anim.0 = false;
-                    ani_time.0 = 0.0;
-                    gp.0 = board.get_tile_coordinates(pos.0);
-                    tp.0 = None;
+        for (entity, motion, is_anim, gp, tp, pos) in (&*entities, &motion, &mut ani, &mut grid_pos, &mut tar_pos, &mut pos).join() {
+            let target = match tp.0 {
+                Some(target) if is_anim.0 => target,
+                _ => {
+                    // Whatever stopped the step, like a respawn, drops it
+                    tweens.remove(entity);
+                    continue;
                 }
+            };
+
+            let starting = match tweens.get(entity) {
+                Some(tween) => tween.to != target,
+                None => true,
+            };
+            if starting {
+                let start = board.get_tile_center_world_coordinate(gp.0);
+                tweens.insert(entity, Tween::new(start, target, motion.duration, motion.easing));
+            }
+
+            let finished = {
+                let tween = tweens.get_mut(entity).unwrap();
+                pos.0 = tween.advance(delta);
+                tween.is_finished()
+            };
+
+            // The tween ends on the target, so the entity lands exactly on
+            // the tile center
+            if finished {
+                tweens.remove(entity);
+                is_anim.0 = false;
+                gp.0 = board.get_tile_coordinates(pos.0);
+                tp.0 = None;
             }
         }
     }
 }
 
-#[derive(Component, Debug)]
-#[component(HashMapStorage)]
-pub struct Controllable;
-
 pub fn init_world<'a, 'b>(world: &mut World,
                           dispatcher_builder: DispatcherBuilder<'a, 'b>)
                           -> DispatcherBuilder<'a, 'b> {
     world.register::<Position>();
+    world.register::<PreviousPosition>();
     world.register::<GridPosition>();
     world.register::<TargetPosition>();
     world.register::<Velocity>();
     world.register::<Animating>();
-    world.register::<AnimationTime>();
+    world.register::<StepMotion>();
+    world.register::<Tween<Vector2<f32>>>();
     world.register::<Direction>();
 
+    // Replaced once a level is loaded
+    world.add_resource(GameBoard(Board::from_tiles(0, 0, 0.0, Vector2::new(0.0, 0.0), Vec::new())));
+
     // Movement happen before we snap into grid cells
-    dispatcher_builder.add(Move, "Move", &[])
+    dispatcher_builder.add(StorePrevious, "StorePrevious", &[])
+        .add(Move, "Move", &["StorePrevious"])
 }
@@ -0,0 +1,159 @@
+
+use specs::{System, VecStorage, Fetch, FetchMut, ReadStorage, WriteStorage, World,
+            DispatcherBuilder};
+
+use components::enemy::Enemy;
+use components::graphics::GameBoard;
+use components::input::Controllable;
+use components::positioning::{Animating, GridPosition, Position, TargetPosition};
+use core::grid::GridCoordinate;
+use resources::occupancy::Occupancy;
+use resources::scene_transitions::{SceneKind, SceneTransitions, Transition};
+
+/// Path an entity has walked over unfilled tiles since it left safe ground.
+#[derive(Component, Debug)]
+#[component(VecStorage)]
+pub struct Trail {
+    pub cells: Vec<GridCoordinate>,
+    /// Last safe tile the entity stood on, used as respawn point.
+    pub safe_tile: GridCoordinate,
+}
+
+impl Trail {
+    pub fn new(safe_tile: GridCoordinate) -> Self {
+        Trail {
+            cells: Vec::new(),
+            safe_tile: safe_tile,
+        }
+    }
+}
+
+/// Number of times the player may still die.
+pub struct Lives(pub u32);
+
+/// Tiles captured during the current tick.
+pub struct CapturedTiles(pub Vec<GridCoordinate>);
+
+pub struct TrackTrail;
+
+impl<'a> System<'a> for TrackTrail {
+    type SystemData = (Fetch<'a, GameBoard>,
+     ReadStorage<'a, Controllable>,
+     ReadStorage<'a, GridPosition>,
+     WriteStorage<'a, Trail>);
+
+    fn run(&mut self, (board, controllable, grid_pos, mut trail): Self::SystemData) {
+        use specs::Join;
+
+        let board = &board.0;
+
+        for (_c, gp, trail) in (&controllable, &grid_pos, &mut trail).join() {
+            if !board.contains(gp.0) {
+                continue;
+            }
+
+            if !board.is_free_move(gp.0) {
+                if trail.cells.last() != Some(&gp.0) {
+                    trail.cells.push(gp.0);
+                }
+            } else if trail.cells.is_empty() {
+                trail.safe_tile = gp.0;
+            }
+        }
+    }
+}
+
+/// Kills the player if an enemy touches the trail before the loop is closed.
+pub struct CutTrail;
+
+// TODO: Type very complicated
+impl<'a> System<'a> for CutTrail {
+    type SystemData = (Fetch<'a, GameBoard>,
+     Fetch<'a, Occupancy>,
+     FetchMut<'a, Lives>,
+     FetchMut<'a, SceneTransitions>,
+     ReadStorage<'a, Enemy>,
+     ReadStorage<'a, Controllable>,
+     WriteStorage<'a, Trail>,
+     WriteStorage<'a, Position>,
+     WriteStorage<'a, GridPosition>,
+     WriteStorage<'a, TargetPosition>,
+     WriteStorage<'a, Animating>);
+
+    fn run(&mut self,
+           (board, occupancy, mut lives, mut transitions, enemy, controllable, mut trail, mut pos, mut grid_pos, mut tar_pos, mut ani): Self::SystemData) {
+        use specs::Join;
+
+        let board = &board.0;
+
+        for (_c, trail, pos, gp, tp, is_anim) in (&controllable, &mut trail, &mut pos, &mut grid_pos, &mut tar_pos, &mut ani).join() {
+            let hit = trail.cells.iter().any(|&c| occupancy.find_at(c, |e| enemy.get(e).is_some()).is_some());
+            if !hit {
+                continue;
+            }
+
+            lives.0 = lives.0.saturating_sub(1);
+            if lives.0 == 0 {
+                transitions.request(Transition::Replace(SceneKind::GameOver));
+            }
+
+            // Respawn on the tile the trail started from
+            trail.cells.clear();
+            pos.0 = board.get_tile_center_world_coordinate(trail.safe_tile);
+            gp.0 = trail.safe_tile;
+            tp.0 = None;
+            is_anim.0 = false;
+        }
+    }
+}
+
+/// Claims the regions a controllable entity closes off when it returns to safe
+/// ground.
+pub struct Capture;
+
+impl<'a> System<'a> for Capture {
+    type SystemData = (FetchMut<'a, GameBoard>,
+     FetchMut<'a, CapturedTiles>,
+     ReadStorage<'a, Enemy>,
+     ReadStorage<'a, Controllable>,
+     ReadStorage<'a, Position>,
+     ReadStorage<'a, GridPosition>,
+     WriteStorage<'a, Trail>);
+
+    fn run(&mut self, (mut board, mut captured, enemy, controllable, pos, grid_pos, mut trail): Self::SystemData) {
+        use specs::Join;
+
+        let board = &mut board.0;
+        captured.0.clear();
+
+        let enemy_tiles: Vec<GridCoordinate> = (&enemy, &pos)
+            .join()
+            .map(|(_e, p)| board.get_tile_coordinates(p.0))
+            .collect();
+
+        for (_c, gp, trail) in (&controllable, &grid_pos, &mut trail).join() {
+            if trail.cells.is_empty() || !board.is_free_move(gp.0) {
+                continue;
+            }
+
+            let cells = board.capture(&trail.cells, &enemy_tiles);
+            captured.0.extend(cells);
+            trail.cells.clear();
+            trail.safe_tile = gp.0;
+        }
+    }
+}
+
+pub fn init_world<'a, 'b>(world: &mut World,
+                          dispatcher_builder: DispatcherBuilder<'a, 'b>)
+                          -> DispatcherBuilder<'a, 'b> {
+    world.register::<Trail>();
+    world.add_resource(Lives(3));
+    world.add_resource(CapturedTiles(Vec::new()));
+
+    // Grid positions are only snapped once the move animation is done, and
+    // a cut trail must never be captured.
+    dispatcher_builder.add(TrackTrail, "TrackTrail", &["Move"])
+        .add(CutTrail, "CutTrail", &["TrackTrail", "IndexOccupancy"])
+        .add(Capture, "Capture", &["CutTrail"])
+}
@@ -0,0 +1,297 @@
+
+use core::grid::GridDirection;
+use core::handle::Handle;
+use core::parse::{self, ParseError};
+use resources::assets::Sprite;
+
+/// What an entity is doing, picks the clip together with its direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Motion {
+    Idle,
+    Walk,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlayMode {
+    Loop,
+    Once,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Frame {
+    pub sprite: Handle<Sprite>,
+    /// Seconds the frame is shown.
+    pub duration: f32,
+}
+
+#[derive(Debug, Clone)]
+pub struct Clip {
+    pub mode: PlayMode,
+    pub frames: Vec<Frame>,
+}
+
+impl Clip {
+    pub fn duration(&self) -> f32 {
+        self.frames.iter().map(|f| f.duration).sum()
+    }
+
+    /// Frame shown a number of seconds into the clip.
+    pub fn frame_at(&self, time: f32) -> &Frame {
+        let mut time = match self.mode {
+            PlayMode::Loop => time % self.duration(),
+            PlayMode::Once => time,
+        };
+
+        for frame in &self.frames {
+            if time < frame.duration {
+                return frame;
+            }
+            time -= frame.duration;
+        }
+        &self.frames[self.frames.len() - 1]
+    }
+
+    /// Whether a one-shot clip has shown its last frame, loops never finish.
+    pub fn is_finished(&self, time: f32) -> bool {
+        self.mode == PlayMode::Once && time >= self.duration()
+    }
+}
+
+/// Clips of one kind of entity, by motion and direction.
+#[derive(Debug, Clone)]
+pub struct ClipSet {
+    clips: Vec<((Motion, GridDirection), Clip)>,
+}
+
+impl ClipSet {
+    pub fn new(clips: Vec<((Motion, GridDirection), Clip)>) -> Self {
+        ClipSet { clips: clips }
+    }
+
+    /// Clip for a motion and direction, the idle clip of the direction when
+    /// the motion has none.
+    pub fn clip(&self, motion: Motion, direction: GridDirection) -> Option<&Clip> {
+        self.find(motion, direction).or_else(|| self.find(Motion::Idle, direction))
+    }
+
+    fn find(&self, motion: Motion, direction: GridDirection) -> Option<&Clip> {
+        self.clips.iter().find(|&&(key, _)| key == (motion, direction)).map(|&(_, ref clip)| clip)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClipSource {
+    pub motion: Motion,
+    pub direction: GridDirection,
+    pub mode: PlayMode,
+    pub frames: Vec<(String, f32)>,
+}
+
+/// Clips described next to an atlas, one per motion and direction, with the
+/// region and duration in seconds of each frame:
+///
+/// ```text
+/// atlas = /sprites/duck.atlas
+/// idle south = loop duck:1
+/// walk south = loop duck_step:0.06 duck:0.06
+/// ```
+///
+/// Motions are `idle` and `walk`, directions `north`, `east`, `south` and
+/// `west`. A `loop` clip starts over after its last frame, a `once` clip
+/// holds it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClipSetSource {
+    pub atlas: String,
+    pub clips: Vec<ClipSource>,
+}
+
+impl ClipSetSource {
+    pub fn parse(source: &str) -> Result<ClipSetSource, ParseError> {
+        let mut atlas = None;
+        let mut clips: Vec<ClipSource> = Vec::new();
+
+        for entry in parse::entries(source) {
+            let entry = entry?;
+            let (key, value) = (entry.key, entry.value);
+
+            if key == "atlas" {
+                atlas = Some(value.to_string());
+                continue;
+            }
+
+            let names: Vec<&str> = key.split_whitespace().collect();
+            if names.len() != 2 {
+                return Err(entry.key_error("expected `<motion> <direction>`"));
+            }
+            let motion = match names[0] {
+                "idle" => Motion::Idle,
+                "walk" => Motion::Walk,
+                other => return Err(entry.key_error(format!("unknown motion `{}`", other))),
+            };
+            let direction = match names[1] {
+                "north" => GridDirection::DirectionNorth,
+                "east" => GridDirection::DirectionEast,
+                "south" => GridDirection::DirectionSouth,
+                "west" => GridDirection::DirectionWest,
+                other => return Err(entry.key_error(format!("unknown direction `{}`", other))),
+            };
+            if clips.iter().any(|c| c.motion == motion && c.direction == direction) {
+                return Err(entry.key_error(format!("clip `{}` defined twice", key)));
+            }
+
+            let mut parts = value.split_whitespace();
+            let mode = match parts.next() {
+                Some("loop") => PlayMode::Loop,
+                Some("once") => PlayMode::Once,
+                _ => return Err(entry.error("expected `<loop|once> <region>:<seconds>...`")),
+            };
+
+            let mut frames = Vec::new();
+            for part in parts {
+                let frame = match part.find(':') {
+                    Some(idx) => (&part[..idx], part[idx + 1..].parse::<f32>()),
+                    None => return Err(entry.error(format!("expected `<region>:<seconds>`, got `{}`", part))),
+                };
+                match frame {
+                    (region, Ok(duration)) if !region.is_empty() && duration.is_finite() && duration > 0.0 => {
+                        frames.push((region.to_string(), duration))
+                    }
+                    _ => return Err(entry.error(format!("invalid frame `{}`", part))),
+                }
+            }
+            if frames.is_empty() {
+                return Err(entry.error("a clip needs at least one frame"));
+            }
+
+            clips.push(ClipSource {
+                motion: motion,
+                direction: direction,
+                mode: mode,
+                frames: frames,
+            });
+        }
+
+        match atlas {
+            Some(atlas) => {
+                Ok(ClipSetSource {
+                    atlas: atlas,
+                    clips: clips,
+                })
+            }
+            None => Err(ParseError::new(parse::last_line(source), "missing `atlas`")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two frames, a quarter and half a second long.
+    fn clip(mode: PlayMode) -> Clip {
+        Clip {
+            mode: mode,
+            frames: vec![Frame {
+                             sprite: Handle::new(0),
+                             duration: 0.25,
+                         },
+                         Frame {
+                             sprite: Handle::new(1),
+                             duration: 0.5,
+                         }],
+        }
+    }
+
+    fn shown(clip: &Clip, time: f32) -> usize {
+        clip.frame_at(time).sprite.index()
+    }
+
+    fn error(source: &str) -> (usize, Option<usize>) {
+        let e = ClipSetSource::parse(source).unwrap_err();
+        (e.line, e.column)
+    }
+
+    #[test]
+    fn loops_wrap_around() {
+        let clip = clip(PlayMode::Loop);
+        assert_eq!(clip.duration(), 0.75);
+
+        assert_eq!(shown(&clip, 0.0), 0);
+        assert_eq!(shown(&clip, 0.25), 1);
+        assert_eq!(shown(&clip, 0.7), 1);
+        assert_eq!(shown(&clip, 0.75), 0);
+        assert_eq!(shown(&clip, 1.0), 1);
+        assert_eq!(shown(&clip, 15.125), 0);
+        assert!(!clip.is_finished(100.0));
+    }
+
+    #[test]
+    fn once_holds_the_last_frame() {
+        let clip = clip(PlayMode::Once);
+
+        assert_eq!(shown(&clip, 0.1), 0);
+        assert_eq!(shown(&clip, 0.5), 1);
+        assert_eq!(shown(&clip, 0.75), 1);
+        assert_eq!(shown(&clip, 100.0), 1);
+        assert!(!clip.is_finished(0.7));
+        assert!(clip.is_finished(0.75));
+    }
+
+    #[test]
+    fn missing_motions_fall_back_to_idle() {
+        let set = ClipSet::new(vec![((Motion::Idle, GridDirection::DirectionNorth), clip(PlayMode::Loop)),
+                                    ((Motion::Walk, GridDirection::DirectionSouth), clip(PlayMode::Once))]);
+
+        assert_eq!(set.clip(Motion::Walk, GridDirection::DirectionSouth).unwrap().mode, PlayMode::Once);
+        assert_eq!(set.clip(Motion::Walk, GridDirection::DirectionNorth).unwrap().mode, PlayMode::Loop);
+        assert!(set.clip(Motion::Idle, GridDirection::DirectionEast).is_none());
+    }
+
+    #[test]
+    fn parses_clips() {
+        let source = ClipSetSource::parse("atlas = /sprites/duck.atlas
+# Standing still
+idle south = loop duck:1
+walk south = once duck_step:0.06 duck:0.125
+")
+            .unwrap();
+
+        assert_eq!(source.atlas, "/sprites/duck.atlas");
+        assert_eq!(source.clips,
+                   vec![ClipSource {
+                            motion: Motion::Idle,
+                            direction: GridDirection::DirectionSouth,
+                            mode: PlayMode::Loop,
+                            frames: vec![("duck".to_string(), 1.0)],
+                        },
+                        ClipSource {
+                            motion: Motion::Walk,
+                            direction: GridDirection::DirectionSouth,
+                            mode: PlayMode::Once,
+                            frames: vec![("duck_step".to_string(), 0.06), ("duck".to_string(), 0.125)],
+                        }]);
+    }
+
+    #[test]
+    fn key_errors() {
+        assert_eq!(error("atlas = /a.atlas\nrun south = loop a:1\n"), (2, Some(1)));
+        assert_eq!(error("atlas = /a.atlas\n  idle up = loop a:1\n"), (2, Some(3)));
+        assert_eq!(error("atlas = /a.atlas\nidle = loop a:1\n"), (2, Some(1)));
+        assert_eq!(error("atlas = /a.atlas\nidle south = loop a:1\nidle south = once a:1\n"), (3, Some(1)));
+        assert_eq!(error("atlas = /a.atlas\nidle south\n"), (2, Some(1)));
+    }
+
+    #[test]
+    fn frame_errors() {
+        for value in &["bounce a:1", "loop", "loop a", "loop a:0", "loop a:-1", "loop :1", "loop a:x", "loop a:inf"] {
+            let source = format!("atlas = /a.atlas\nidle south = {}\n", value);
+            assert_eq!(error(&source), (2, Some(14)), "idle south = {}", value);
+        }
+    }
+
+    #[test]
+    fn atlas_is_required() {
+        assert_eq!(error("idle south = loop a:1\n\nwalk south = loop a:1\n"), (3, None));
+        assert_eq!(error(""), (1, None));
+    }
+}
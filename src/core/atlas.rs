@@ -0,0 +1,108 @@
+
+use core::parse::{self, ParseError};
+
+/// Area of an atlas image in pixels, as `[x, y, width, height]`.
+pub type Region = [u32; 4];
+
+/// Sidecar file of an atlas image, naming rectangles on it:
+///
+/// ```text
+/// image = /sprites/duck.png
+/// duck = 0 0 50 48
+/// ```
+///
+/// Every key other than `image` is a region, given as `x y width height` in
+/// pixels from the top left corner of the image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AtlasSource {
+    pub image: String,
+    pub regions: Vec<(String, Region)>,
+}
+
+impl AtlasSource {
+    pub fn parse(source: &str) -> Result<AtlasSource, ParseError> {
+        let mut image = None;
+        let mut regions: Vec<(String, Region)> = Vec::new();
+
+        for entry in parse::entries(source) {
+            let entry = entry?;
+            let (key, value) = (entry.key, entry.value);
+
+            if key == "image" {
+                image = Some(value.to_string());
+                continue;
+            }
+
+            if regions.iter().any(|&(ref name, _)| name == key) {
+                return Err(entry.key_error(format!("region `{}` defined twice", key)));
+            }
+
+            let numbers: Vec<u32> = match value.split_whitespace().map(|n| n.parse()).collect() {
+                Ok(numbers) => numbers,
+                Err(_) => return Err(entry.error(format!("invalid region `{}`", value))),
+            };
+            if numbers.len() != 4 || numbers[2] == 0 || numbers[3] == 0 {
+                return Err(entry.error("expected `<x> <y> <width> <height>`"));
+            }
+
+            regions.push((key.to_string(), [numbers[0], numbers[1], numbers[2], numbers[3]]));
+        }
+
+        match image {
+            Some(image) => {
+                Ok(AtlasSource {
+                    image: image,
+                    regions: regions,
+                })
+            }
+            None => Err(ParseError::new(parse::last_line(source), "missing `image`")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(source: &str) -> (usize, Option<usize>) {
+        let e = AtlasSource::parse(source).unwrap_err();
+        (e.line, e.column)
+    }
+
+    #[test]
+    fn parses_regions() {
+        let atlas = AtlasSource::parse("# Ducks
+image = /sprites/duck.png
+duck = 0 0 50 48
+
+duck_step =  50 0 50 48
+")
+            .unwrap();
+
+        assert_eq!(atlas,
+                   AtlasSource {
+                       image: "/sprites/duck.png".to_string(),
+                       regions: vec![("duck".to_string(), [0, 0, 50, 48]), ("duck_step".to_string(), [50, 0, 50, 48])],
+                   });
+    }
+
+    #[test]
+    fn region_defined_twice() {
+        assert_eq!(error("image = /a.png\nduck = 0 0 1 1\nduck = 1 0 1 1\n"), (3, Some(1)));
+    }
+
+    #[test]
+    fn malformed_lines() {
+        assert_eq!(error("image = /a.png\nduck 0 0 1 1\n"), (2, Some(1)));
+        assert_eq!(error("image = /a.png\nduck = 0 0 1\n"), (2, Some(8)));
+        assert_eq!(error("image = /a.png\nduck = 0 0 1 1 1\n"), (2, Some(8)));
+        assert_eq!(error("image = /a.png\nduck = 0 -1 1 1\n"), (2, Some(8)));
+        assert_eq!(error("image = /a.png\nduck = 0 0 0 1\n"), (2, Some(8)));
+        assert_eq!(error("image = /a.png\nduck =\n"), (2, Some(7)));
+    }
+
+    #[test]
+    fn image_is_required() {
+        assert_eq!(error("duck = 0 0 1 1\n"), (1, None));
+    }
+}
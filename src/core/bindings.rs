@@ -0,0 +1,349 @@
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{Read, Write};
+
+use ggez::{Context, GameError, GameResult};
+use ggez::event::{Axis, Button, Keycode};
+
+use components::input::{ALL_ACTIONS, AxisDirection, DirectionPolicy, InputAction, InputBinding};
+use core::parse::{self, ParseError};
+
+/// Default part of a stick's travel that is ignored.
+pub const DEFAULT_DEAD_ZONE: f32 = 0.35;
+
+/// Where the bindings are kept in the user directory.
+pub const BINDINGS_PATH: &str = "/bindings.cfg";
+
+/// User configurable input bindings, stored as text in the user directory
+/// with one line per action listing everything that triggers it:
+///
+/// ```text
+/// dead_zone = 0.35
+/// policy = most_recent
+/// up = key:W, key:Up, button:dpup, axis:lefty-
+/// down = key:S, key:Down, button:dpdown, axis:lefty+
+/// ```
+///
+/// Keys use the SDL key names, buttons and axes the SDL controller names with
+/// the half of the axis appended. A binding may only trigger one action.
+/// The policy decides which direction wins when several are held, one of
+/// `most_recent`, `first_pressed` or `cancel_opposite`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Bindings {
+    actions: HashMap<InputAction, Vec<InputBinding>>,
+    pub dead_zone: f32,
+    pub policy: DirectionPolicy,
+}
+
+/// A binding could not be added because another action uses it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Conflict {
+    pub binding: InputBinding,
+    pub action: InputAction,
+}
+
+impl Bindings {
+    /// Bindings without anything bound.
+    pub fn empty() -> Self {
+        Bindings {
+            actions: HashMap::new(),
+            dead_zone: DEFAULT_DEAD_ZONE,
+            policy: DirectionPolicy::MostRecent,
+        }
+    }
+
+    /// WASD and the arrow keys, the d-pad and the left stick.
+    pub fn default() -> Self {
+        let mut bindings = Bindings::empty();
+
+        // SDL sticks point down for positive y
+        let defaults = [(InputAction::MoveUp, Keycode::W, Keycode::Up, Button::DPadUp, Axis::LeftY, AxisDirection::Negative),
+                        (InputAction::MoveDown, Keycode::S, Keycode::Down, Button::DPadDown, Axis::LeftY, AxisDirection::Positive),
+                        (InputAction::MoveLeft, Keycode::A, Keycode::Left, Button::DPadLeft, Axis::LeftX, AxisDirection::Negative),
+                        (InputAction::MoveRight, Keycode::D, Keycode::Right, Button::DPadRight, Axis::LeftX, AxisDirection::Positive)];
+
+        for &(action, key, arrow, btn, axis, half) in &defaults {
+            bindings.actions.insert(action,
+                                    vec![InputBinding::Key(key),
+                                         InputBinding::Key(arrow),
+                                         InputBinding::Button(btn),
+                                         InputBinding::Axis(axis, half)]);
+        }
+        bindings
+    }
+
+    /// Read the bindings from the user directory, writing the defaults there
+    /// when there are none yet.
+    pub fn load(ctx: &mut Context, path: &str) -> GameResult<Bindings> {
+        if !ctx.filesystem.is_file(path) {
+            let bindings = Bindings::default();
+            bindings.save(ctx, path)?;
+            return Ok(bindings);
+        }
+
+        let mut file = ctx.filesystem.open(path)?;
+        let mut source = String::new();
+        file.read_to_string(&mut source)?;
+
+        Bindings::parse(&source).map_err(|e| GameError::ResourceLoadError(format!("{}: {}", path, e)))
+    }
+
+    pub fn save(&self, ctx: &mut Context, path: &str) -> GameResult<()> {
+        let mut file = ctx.filesystem.create(path)?;
+        write!(file, "{}", self)?;
+        Ok(())
+    }
+
+    pub fn parse(source: &str) -> Result<Bindings, ParseError> {
+        let mut bindings = Bindings::empty();
+
+        for entry in parse::entries(source) {
+            let entry = entry?;
+            let (key, value) = (entry.key, entry.value);
+
+            if key == "dead_zone" {
+                bindings.dead_zone = match value.parse::<f32>() {
+                    Ok(v) if v >= 0.0 && v < 1.0 => v,
+                    _ => return Err(entry.error(format!("invalid dead zone `{}`", value))),
+                };
+                continue;
+            }
+
+            if key == "policy" {
+                bindings.policy = match DirectionPolicy::from_name(value) {
+                    Some(policy) => policy,
+                    None => return Err(entry.error(format!("unknown policy `{}`", value))),
+                };
+                continue;
+            }
+
+            let action = match InputAction::from_name(key) {
+                Some(action) => action,
+                None => return Err(entry.key_error(format!("unknown action `{}`", key))),
+            };
+
+            for item in value.split(',').map(|s| s.trim()).filter(|s| !s.is_empty()) {
+                let binding = match parse_binding(item) {
+                    Some(binding) => binding,
+                    None => return Err(entry.error(format!("invalid binding `{}`", item))),
+                };
+                if let Err(conflict) = bindings.bind(action, binding) {
+                    return Err(entry.error(format!("`{}` is already bound to `{}`",
+                                                   binding_name(conflict.binding),
+                                                   conflict.action.name())));
+                }
+            }
+        }
+
+        Ok(bindings)
+    }
+
+    /// Everything bound to an action.
+    pub fn get(&self, action: InputAction) -> &[InputBinding] {
+        match self.actions.get(&action) {
+            Some(bindings) => bindings.as_slice(),
+            None => &[],
+        }
+    }
+
+    /// Action a binding triggers, if any.
+    pub fn action_for(&self, binding: InputBinding) -> Option<InputAction> {
+        ALL_ACTIONS.iter().cloned().find(|&a| self.get(a).contains(&binding))
+    }
+
+    /// Add a binding to an action, refused when another action uses it.
+    pub fn bind(&mut self, action: InputAction, binding: InputBinding) -> Result<(), Conflict> {
+        match self.action_for(binding) {
+            Some(other) if other == action => Ok(()),
+            Some(other) => {
+                Err(Conflict {
+                    binding: binding,
+                    action: other,
+                })
+            }
+            None => {
+                self.actions.entry(action).or_insert_with(Vec::new).push(binding);
+                Ok(())
+            }
+        }
+    }
+
+    /// Add a binding to an action, taking it away from the action that used
+    /// it before. Returns that action.
+    pub fn rebind(&mut self, action: InputAction, binding: InputBinding) -> Option<InputAction> {
+        let previous = match self.action_for(binding) {
+            Some(other) if other != action => Some(other),
+            _ => None,
+        };
+        if let Some(other) = previous {
+            self.unbind(other, binding);
+        }
+        let _ = self.bind(action, binding);
+        previous
+    }
+
+    /// Remove a binding from an action, returns false if it was not bound.
+    pub fn unbind(&mut self, action: InputAction, binding: InputBinding) -> bool {
+        let removed = match self.actions.get_mut(&action) {
+            Some(bindings) => {
+                let before = bindings.len();
+                bindings.retain(|&b| b != binding);
+                bindings.len() != before
+            }
+            None => return false,
+        };
+
+        // Keep unbound actions out of the map, like `parse` does
+        if self.get(action).is_empty() {
+            self.actions.remove(&action);
+        }
+        removed
+    }
+
+    /// Remove everything bound to an action.
+    pub fn clear(&mut self, action: InputAction) {
+        self.actions.remove(&action);
+    }
+}
+
+impl fmt::Display for Bindings {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "dead_zone = {}", self.dead_zone)?;
+        writeln!(f, "policy = {}", self.policy.name())?;
+
+        for &action in &ALL_ACTIONS {
+            let items: Vec<String> = self.get(action).iter().map(|&b| binding_name(b)).collect();
+            writeln!(f, "{} = {}", action.name(), items.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+fn binding_name(binding: InputBinding) -> String {
+    match binding {
+        InputBinding::Key(code) => format!("key:{}", code.name()),
+        InputBinding::Button(btn) => format!("button:{}", btn.string()),
+        InputBinding::Axis(axis, AxisDirection::Positive) => format!("axis:{}+", axis.string()),
+        InputBinding::Axis(axis, AxisDirection::Negative) => format!("axis:{}-", axis.string()),
+    }
+}
+
+fn parse_binding(item: &str) -> Option<InputBinding> {
+    let idx = item.find(':')?;
+    let (device, name) = (&item[..idx], &item[idx + 1..]);
+
+    match device {
+        "key" => Keycode::from_name(name).map(InputBinding::Key),
+        "button" => Button::from_string(name).map(InputBinding::Button),
+        "axis" => {
+            let half = if name.ends_with('+') {
+                AxisDirection::Positive
+            } else if name.ends_with('-') {
+                AxisDirection::Negative
+            } else {
+                return None;
+            };
+            Axis::from_string(&name[..name.len() - 1]).map(|axis| InputBinding::Axis(axis, half))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const UP: InputAction = InputAction::MoveUp;
+    const RIGHT: InputAction = InputAction::MoveRight;
+
+    #[test]
+    fn bind_refuses_conflicts() {
+        let mut bindings = Bindings::default();
+        let before = bindings.get(UP).to_vec();
+
+        assert_eq!(bindings.bind(UP, InputBinding::Key(Keycode::D)),
+                   Err(Conflict {
+                       binding: InputBinding::Key(Keycode::D),
+                       action: RIGHT,
+                   }));
+        assert_eq!(bindings.get(UP), before.as_slice());
+        assert_eq!(bindings.action_for(InputBinding::Key(Keycode::D)), Some(RIGHT));
+
+        // Binding again to the same action changes nothing
+        assert_eq!(bindings.bind(UP, InputBinding::Key(Keycode::W)), Ok(()));
+        assert_eq!(bindings.get(UP), before.as_slice());
+    }
+
+    #[test]
+    fn several_bindings_per_action() {
+        let mut bindings = Bindings::empty();
+        let items = [InputBinding::Key(Keycode::W),
+                     InputBinding::Key(Keycode::K),
+                     InputBinding::Button(Button::Y),
+                     InputBinding::Axis(Axis::RightY, AxisDirection::Negative)];
+
+        for &item in &items {
+            bindings.bind(UP, item).unwrap();
+        }
+        assert_eq!(bindings.get(UP), &items[..]);
+        for &item in &items {
+            assert_eq!(bindings.action_for(item), Some(UP));
+        }
+        assert!(bindings.get(RIGHT).is_empty());
+        assert_eq!(bindings.action_for(InputBinding::Axis(Axis::RightY, AxisDirection::Positive)), None);
+    }
+
+    #[test]
+    fn rebind_unbind_and_clear() {
+        let mut bindings = Bindings::default();
+        let d = InputBinding::Key(Keycode::D);
+
+        assert_eq!(bindings.rebind(UP, d), Some(RIGHT));
+        assert_eq!(bindings.action_for(d), Some(UP));
+        assert_eq!(bindings.rebind(UP, d), None);
+
+        assert!(bindings.unbind(UP, d));
+        assert!(!bindings.unbind(UP, d));
+        assert_eq!(bindings.action_for(d), None);
+
+        bindings.clear(RIGHT);
+        assert!(bindings.get(RIGHT).is_empty());
+        assert!(!bindings.unbind(RIGHT, InputBinding::Key(Keycode::Right)));
+    }
+
+    #[test]
+    fn display_round_trips() {
+        let default = Bindings::default();
+        assert_eq!(Bindings::parse(&default.to_string()).unwrap(), default);
+
+        let mut custom = Bindings::empty();
+        custom.dead_zone = 0.125;
+        custom.policy = DirectionPolicy::CancelOpposite;
+        custom.bind(UP, InputBinding::Key(Keycode::LShift)).unwrap();
+        custom.bind(UP, InputBinding::Axis(Axis::TriggerLeft, AxisDirection::Positive)).unwrap();
+        custom.bind(RIGHT, InputBinding::Button(Button::RightShoulder)).unwrap();
+        custom.bind(RIGHT, InputBinding::Key(Keycode::Kp6)).unwrap();
+        // Emptied actions are written as empty lines
+        custom.bind(InputAction::MoveDown, InputBinding::Key(Keycode::S)).unwrap();
+        custom.unbind(InputAction::MoveDown, InputBinding::Key(Keycode::S));
+
+        let text = custom.to_string();
+        assert!(text.contains("\ndown = \n"), "{}", text);
+        assert_eq!(Bindings::parse(&text).unwrap(), custom);
+    }
+
+    #[test]
+    fn parse_errors() {
+        let error = |source: &str| {
+            let e = Bindings::parse(source).unwrap_err();
+            (e.line, e.column)
+        };
+
+        assert_eq!(error("up = key:W\ndown = key:W\n"), (2, Some(8)));
+        assert_eq!(error("up = key:W, key:Nope\n"), (1, Some(6)));
+        assert_eq!(error("jump = key:W\n"), (1, Some(1)));
+        assert_eq!(error("dead_zone = 1\n"), (1, Some(13)));
+        assert_eq!(error("policy = random\n"), (1, Some(10)));
+    }
+}
@@ -0,0 +1,231 @@
+
+use cgmath::{InnerSpace, Vector2};
+
+/// Thresholds telling swipes and taps apart, distances are in screen pixels
+/// and times in seconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GestureConfig {
+    /// Shortest movement counted as a swipe.
+    pub min_swipe_distance: f32,
+    /// Slowest average speed, in pixels per second, counted as a swipe.
+    pub min_swipe_velocity: f32,
+    /// Longest movement still counted as a tap.
+    pub max_tap_distance: f32,
+    /// Longest press still counted as a tap.
+    pub max_tap_duration: f32,
+}
+
+impl Default for GestureConfig {
+    fn default() -> Self {
+        GestureConfig {
+            min_swipe_distance: 40.0,
+            min_swipe_velocity: 200.0,
+            max_tap_distance: 12.0,
+            max_tap_duration: 0.3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TouchPhase {
+    Began,
+    Moved,
+    Ended,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TouchEvent {
+    pub phase: TouchPhase,
+    pub position: Vector2<f32>,
+    /// Seconds since any fixed point, only differences are used.
+    pub time: f32,
+}
+
+impl TouchEvent {
+    pub fn new(phase: TouchPhase, x: f32, y: f32, time: f32) -> Self {
+        TouchEvent {
+            phase: phase,
+            position: Vector2::new(x, y),
+            time: time,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Gesture {
+    /// Finger moved fast enough, holds the movement in screen space.
+    Swipe(Vector2<f32>),
+    /// Short press in place, holds the screen point pressed.
+    Tap(Vector2<f32>),
+}
+
+/// Recognizes one finger at a time.
+///
+/// Only knows about screen points and timestamps, what a swipe or a tap means
+/// in the game is up to whoever consumes the gestures.
+#[derive(Debug)]
+pub struct GestureRecognizer {
+    pub config: GestureConfig,
+    // Where and when the current touch started
+    start: Option<(Vector2<f32>, f32)>,
+    // A swipe was reported already, ignore the rest of the touch
+    fired: bool,
+}
+
+impl GestureRecognizer {
+    pub fn new(config: GestureConfig) -> Self {
+        GestureRecognizer {
+            config: config,
+            start: None,
+            fired: false,
+        }
+    }
+
+    /// Feed the next event, returns a gesture once one is recognized.
+    ///
+    /// Swipes are reported as soon as they pass the thresholds so they do not
+    /// wait for the finger to lift, taps are reported when it does.
+    pub fn handle(&mut self, event: TouchEvent) -> Option<Gesture> {
+        match event.phase {
+            TouchPhase::Began => {
+                self.start = Some((event.position, event.time));
+                self.fired = false;
+                None
+            }
+            TouchPhase::Moved => {
+                if self.fired {
+                    return None;
+                }
+                let swipe = self.swipe(event);
+                if swipe.is_some() {
+                    self.fired = true;
+                }
+                swipe
+            }
+            TouchPhase::Ended => {
+                let gesture = if self.fired {
+                    None
+                } else {
+                    self.swipe(event).or_else(|| self.tap(event))
+                };
+                self.start = None;
+                self.fired = false;
+                gesture
+            }
+            TouchPhase::Cancelled => {
+                self.start = None;
+                self.fired = false;
+                None
+            }
+        }
+    }
+
+    fn swipe(&self, event: TouchEvent) -> Option<Gesture> {
+        let (start, started) = self.start?;
+        let offset = event.position - start;
+        let distance = offset.magnitude();
+        let duration = (event.time - started).max(::std::f32::EPSILON);
+
+        if distance >= self.config.min_swipe_distance && distance / duration >= self.config.min_swipe_velocity {
+            Some(Gesture::Swipe(offset))
+        } else {
+            None
+        }
+    }
+
+    fn tap(&self, event: TouchEvent) -> Option<Gesture> {
+        let (start, started) = self.start?;
+        let distance = (event.position - start).magnitude();
+
+        if distance <= self.config.max_tap_distance && event.time - started <= self.config.max_tap_duration {
+            Some(Gesture::Tap(start))
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recognizer() -> GestureRecognizer {
+        GestureRecognizer::new(GestureConfig::default())
+    }
+
+    fn feed(recognizer: &mut GestureRecognizer, events: &[(TouchPhase, f32, f32, f32)]) -> Vec<Option<Gesture>> {
+        events.iter()
+            .map(|&(phase, x, y, time)| recognizer.handle(TouchEvent::new(phase, x, y, time)))
+            .collect()
+    }
+
+    #[test]
+    fn swipe_fires_while_moving() {
+        let mut r = recognizer();
+        let gestures = feed(&mut r,
+                            &[(TouchPhase::Began, 100.0, 100.0, 1.0),
+                              (TouchPhase::Moved, 110.0, 100.0, 1.02),
+                              (TouchPhase::Moved, 160.0, 90.0, 1.1)]);
+        assert_eq!(gestures,
+                   vec![None, None, Some(Gesture::Swipe(Vector2::new(60.0, -10.0)))]);
+    }
+
+    #[test]
+    fn swipe_reported_on_release() {
+        let mut r = recognizer();
+        let gestures = feed(&mut r,
+                            &[(TouchPhase::Began, 0.0, 0.0, 0.0),
+                              (TouchPhase::Moved, 0.0, 10.0, 0.05),
+                              (TouchPhase::Ended, 0.0, 60.0, 0.1)]);
+        assert_eq!(gestures, vec![None, None, Some(Gesture::Swipe(Vector2::new(0.0, 60.0)))]);
+    }
+
+    #[test]
+    fn short_press_is_a_tap() {
+        let mut r = recognizer();
+        let gestures = feed(&mut r,
+                            &[(TouchPhase::Began, 50.0, 70.0, 2.0),
+                              (TouchPhase::Moved, 53.0, 72.0, 2.05),
+                              (TouchPhase::Ended, 54.0, 72.0, 2.1)]);
+        assert_eq!(gestures, vec![None, None, Some(Gesture::Tap(Vector2::new(50.0, 70.0)))]);
+    }
+
+    #[test]
+    fn slow_press_is_no_tap() {
+        let mut r = recognizer();
+        let gestures = feed(&mut r,
+                            &[(TouchPhase::Began, 50.0, 70.0, 2.0),
+                              (TouchPhase::Ended, 50.0, 70.0, 2.5)]);
+        assert_eq!(gestures, vec![None, None]);
+    }
+
+    #[test]
+    fn cancelled_touch_reports_nothing() {
+        let mut r = recognizer();
+        let gestures = feed(&mut r,
+                            &[(TouchPhase::Began, 0.0, 0.0, 0.0),
+                              (TouchPhase::Moved, 5.0, 0.0, 0.05),
+                              (TouchPhase::Cancelled, 5.0, 0.0, 0.06),
+                              (TouchPhase::Moved, 80.0, 0.0, 0.08),
+                              (TouchPhase::Ended, 80.0, 0.0, 0.1)]);
+        assert_eq!(gestures, vec![None; 5]);
+    }
+
+    #[test]
+    fn one_swipe_per_touch() {
+        let mut r = recognizer();
+        let gestures = feed(&mut r,
+                            &[(TouchPhase::Began, 0.0, 0.0, 0.0),
+                              (TouchPhase::Moved, 50.0, 0.0, 0.1),
+                              (TouchPhase::Moved, 150.0, 0.0, 0.2),
+                              (TouchPhase::Ended, 200.0, 0.0, 0.25)]);
+        assert_eq!(gestures, vec![None, Some(Gesture::Swipe(Vector2::new(50.0, 0.0))), None, None]);
+
+        // The latch is reset by the next touch
+        let gestures = feed(&mut r,
+                            &[(TouchPhase::Began, 0.0, 0.0, 1.0),
+                              (TouchPhase::Ended, 0.0, 0.0, 1.1)]);
+        assert_eq!(gestures, vec![None, Some(Gesture::Tap(Vector2::new(0.0, 0.0)))]);
+    }
+}
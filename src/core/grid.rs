@@ -3,17 +3,23 @@ use ggez::*;
 use ggez::graphics::DrawMode;
 use cgmath::Vector2;
 
+use core::handle::Handle;
 use core::iso_coords::IsoCoord;
+use core::render_queue::{DrawCommand, Layer, RenderQueue};
+use core::tileset::TileSet;
+use resources::assets::{Assets, Sprite};
+use resources::camera::Camera;
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Tile {
-    sprite: u8,
+    kind: u8,
+    sprite: Option<Handle<Sprite>>,
     free_movement: bool,
 }
 
 pub type GridCoordinate = [u32; 2];
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum GridDirection {
     DirectionNorth,
     DirectionSouth,
@@ -21,16 +27,46 @@ pub enum GridDirection {
     DirectionWest,
 }
 
+pub const ALL_DIRECTIONS: [GridDirection; 4] = [GridDirection::DirectionNorth,
+                                                 GridDirection::DirectionSouth,
+                                                 GridDirection::DirectionEast,
+                                                 GridDirection::DirectionWest];
+
+/// Number of steps between two cells when moving along the grid.
+pub fn manhattan_distance(a: GridCoordinate, b: GridCoordinate) -> u32 {
+    let dx = if a[0] > b[0] { a[0] - b[0] } else { b[0] - a[0] };
+    let dy = if a[1] > b[1] { a[1] - b[1] } else { b[1] - a[1] };
+    dx + dy
+}
+
 impl Tile {
     fn new() -> Tile {
         Tile {
-            // TODO: Use some kind of Sprite UUID and something managing assets.
-            sprite: 0,
+            kind: 0,
+            sprite: None,
             free_movement: false,
         }
     }
 
-    fn set_sprite(&mut self, sprite: u8) {
+    /// Construct a tile of a given type, without a sprite.
+    pub fn with_type(kind: u8, free_movement: bool) -> Tile {
+        Tile {
+            kind: kind,
+            sprite: None,
+            free_movement: free_movement,
+        }
+    }
+
+    fn set_kind(&mut self, kind: u8) {
+        self.kind = kind;
+    }
+
+    /// Sprite drawn for the tile, the outline is drawn when there is none.
+    pub fn sprite(&self) -> Option<Handle<Sprite>> {
+        self.sprite
+    }
+
+    fn set_sprite(&mut self, sprite: Option<Handle<Sprite>>) {
         self.sprite = sprite;
     }
 
@@ -38,16 +74,23 @@ impl Tile {
         self.free_movement = b;
     }
 
-    fn is_free_move(&self) -> bool {
+    pub fn is_free_move(&self) -> bool {
         self.free_movement
     }
 
-    fn get_tile_type(&self) -> u8 {
-        self.sprite
+    /// Turn the tile into safe ground.
+    fn fill(&mut self) {
+        self.set_kind(1);
+        self.set_sprite(None);
+        self.set_free_move(true);
+    }
+
+    pub fn get_tile_type(&self) -> u8 {
+        self.kind
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct Board {
     width: u32,
     height: u32,
@@ -66,11 +109,11 @@ impl Board {
             for j in 0..width {
                 let mut new_tile = Tile::new();
                 if i % (height - 1) == 0 {
-                    new_tile.set_sprite(1);
+                    new_tile.set_kind(1);
                     new_tile.set_free_move(true);
                 }
                 if j == 0 || j == (width - 1) {
-                    new_tile.set_sprite(1);
+                    new_tile.set_kind(1);
                     new_tile.set_free_move(true);
                 }
                 tiles.push(new_tile);
@@ -86,38 +129,48 @@ impl Board {
         }
     }
 
+    /// Construct a board from tiles laid out row by row.
+    pub fn from_tiles(width: u32,
+                      height: u32,
+                      tile_size: f32,
+                      pos: Vector2<f32>,
+                      tiles: Vec<Tile>)
+                      -> Self {
+        assert_eq!(tiles.len(), (width * height) as usize);
+
+        Board {
+            width: width,
+            height: height,
+            tile_size: tile_size,
+            position: pos,
+            tiles: tiles,
+        }
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    pub fn tile_size(&self) -> f32 {
+        self.tile_size
+    }
+
+    /// Tile next to another in a direction, none when it would be off the
+    /// board. Whether the tile may be entered is up to collision checks.
     pub fn get_neighbour(&self,
                          coord: GridCoordinate,
                          direction: GridDirection)
                          -> Option<GridCoordinate> {
-
-        // TODO: Move out bound checks into collision detection.
-        //       Pretty ungly shit going on here
         match direction {
-            GridDirection::DirectionNorth => {
-                if coord[1] == 0 {
-                    return None;
-                }
-                Some([coord[0], coord[1] - 1])
-            }
-            GridDirection::DirectionSouth => {
-                if coord[1] == self.height {
-                    return None;
-                }
-                Some([coord[0], coord[1] + 1])
-            }
-            GridDirection::DirectionEast => {
-                if coord[0] == self.height {
-                    return None;
-                }
-                Some([coord[0] + 1, coord[1]])
-            }
-            GridDirection::DirectionWest => {
-                if coord[0] == 0 {
-                    return None;
-                }
-                Some([coord[0] - 1, coord[1]])
-            }
+            GridDirection::DirectionNorth if coord[1] > 0 => Some([coord[0], coord[1] - 1]),
+            GridDirection::DirectionSouth if coord[1] + 1 < self.height => Some([coord[0], coord[1] + 1]),
+            GridDirection::DirectionEast if coord[0] + 1 < self.width => Some([coord[0] + 1, coord[1]]),
+            GridDirection::DirectionWest if coord[0] > 0 => Some([coord[0] - 1, coord[1]]),
+            _ => None,
         }
     }
 
@@ -126,18 +179,165 @@ impl Board {
          ((point.y - self.position.y) / self.tile_size).floor() as u32]
     }
 
+    /// Smallest and largest isometric coordinates covered by the board.
+    pub fn iso_bounds(&self) -> (Vector2<f32>, Vector2<f32>) {
+        let w = self.width as f32 * self.tile_size;
+        let h = self.height as f32 * self.tile_size;
+        let corners = [IsoCoord::from_cartesian(self.position.x, self.position.y),
+                       IsoCoord::from_cartesian(self.position.x + w, self.position.y),
+                       IsoCoord::from_cartesian(self.position.x + w, self.position.y + h),
+                       IsoCoord::from_cartesian(self.position.x, self.position.y + h)];
+
+        let mut min = Vector2::new(corners[0].x, corners[0].y);
+        let mut max = min;
+        for c in &corners[1..] {
+            min.x = min.x.min(c.x);
+            min.y = min.y.min(c.y);
+            max.x = max.x.max(c.x);
+            max.y = max.y.max(c.y);
+        }
+        (min, max)
+    }
+
+    /// Tile under a world point, or `None` if the point is off the board.
+    pub fn tile_at(&self, point: Vector2<f32>) -> Option<GridCoordinate> {
+        if point.x < self.position.x || point.y < self.position.y {
+            return None;
+        }
+
+        let coord = self.get_tile_coordinates(point);
+        if self.contains(coord) {
+            Some(coord)
+        } else {
+            None
+        }
+    }
+
+    /// Tile drawn under a screen point, taking the board origin and the
+    /// camera into account.
+    pub fn tile_at_screen(&self, point: graphics::Point, camera: &Camera) -> Option<GridCoordinate> {
+        self.tile_at(camera.screen_to_world(point))
+    }
+
     pub fn get_tile_center_world_coordinate(&self, cell: GridCoordinate) -> Vector2<f32> {
         Vector2::new(self.position.x + (cell[0] as f32 + 0.5) * self.tile_size,
                      self.position.y + (cell[1] as f32 + 0.5) * self.tile_size)
     }
 
-    #[allow(dead_code)]
     pub fn get_tile(&self, coord: GridCoordinate) -> &Tile {
-        let i = coord[0] + self.width * coord[1];
-        &self.tiles[i as usize]
+        &self.tiles[self.index(coord)]
+    }
+
+    pub fn set_sprite(&mut self, coord: GridCoordinate, sprite: Option<Handle<Sprite>>) {
+        let i = self.index(coord);
+        self.tiles[i].set_sprite(sprite);
     }
 
-    pub fn render(&self, ctx: &mut Context) -> GameResult<()> {
+    /// Check if a coordinate lies on the board.
+    pub fn contains(&self, coord: GridCoordinate) -> bool {
+        coord[0] < self.width && coord[1] < self.height
+    }
+
+    /// Check if the tile at a coordinate is safe to move on.
+    pub fn is_free_move(&self, coord: GridCoordinate) -> bool {
+        self.contains(coord) && self.get_tile(coord).is_free_move()
+    }
+
+    /// Percentage of the tiles that are safe to move on.
+    pub fn fill_percentage(&self) -> f32 {
+        if self.tiles.is_empty() {
+            return 0.0;
+        }
+
+        let filled = self.tiles.iter().filter(|t| t.is_free_move()).count();
+        filled as f32 * 100.0 / self.tiles.len() as f32
+    }
+
+    /// Capture territory after a trail has been closed off.
+    ///
+    /// The trail cells are filled first, then every open region that can not
+    /// be reached by any of the enemies is flood-filled. Returns all cells that
+    /// were converted to safe ground.
+    pub fn capture(&mut self,
+                   trail: &[GridCoordinate],
+                   enemies: &[GridCoordinate])
+                   -> Vec<GridCoordinate> {
+        let mut captured = Vec::new();
+
+        for &cell in trail {
+            if self.contains(cell) && !self.is_free_move(cell) {
+                let i = self.index(cell);
+                self.tiles[i].fill();
+                captured.push(cell);
+            }
+        }
+
+        // Mark everything the enemies can still reach, that stays open
+        let mut reachable = vec![false; self.tiles.len()];
+        let mut stack: Vec<GridCoordinate> = enemies.iter()
+            .cloned()
+            .filter(|&c| self.contains(c))
+            .collect();
+
+        while let Some(cell) = stack.pop() {
+            let i = self.index(cell);
+            if reachable[i] || self.tiles[i].is_free_move() {
+                continue;
+            }
+            reachable[i] = true;
+            stack.extend(self.adjacent(cell));
+        }
+
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let i = self.index([x, y]);
+                if !reachable[i] && !self.tiles[i].is_free_move() {
+                    self.tiles[i].fill();
+                    captured.push([x, y]);
+                }
+            }
+        }
+
+        captured
+    }
+
+    /// All coordinates on the board sharing an edge with the given one.
+    pub fn adjacent(&self, coord: GridCoordinate) -> Vec<GridCoordinate> {
+        let mut cells = Vec::with_capacity(4);
+
+        if coord[0] > 0 {
+            cells.push([coord[0] - 1, coord[1]]);
+        }
+        if coord[1] > 0 {
+            cells.push([coord[0], coord[1] - 1]);
+        }
+        if coord[0] + 1 < self.width {
+            cells.push([coord[0] + 1, coord[1]]);
+        }
+        if coord[1] + 1 < self.height {
+            cells.push([coord[0], coord[1] + 1]);
+        }
+
+        cells
+    }
+
+    fn index(&self, coord: GridCoordinate) -> usize {
+        (coord[0] + self.width * coord[1]) as usize
+    }
+
+    /// How far back a world point is drawn, the row plus the column of the
+    /// tile under it, fractional between tile centers.
+    pub fn depth_of(&self, point: Vector2<f32>) -> f32 {
+        (point.x - self.position.x + point.y - self.position.y) / self.tile_size - 1.0
+    }
+
+    /// Queue the tiles, textured where the tile has a sprite and as an
+    /// outline otherwise.
+    pub fn render(&self,
+                  queue: &mut RenderQueue<DrawCommand>,
+                  camera: &Camera,
+                  assets: &Assets,
+                  tile_set: &TileSet) {
         for i in 0..self.height {
             for j in 0..self.width {
 
@@ -145,24 +345,224 @@ impl Board {
                 // upper left corner.
                 let x = self.position.x + j as f32 * self.tile_size;
                 let y = self.position.y + i as f32 * self.tile_size;
+                let depth = (i + j) as f32;
 
-                if self.tiles[(i + self.width * j) as usize].get_tile_type() == 0 {
-                    graphics::set_color(ctx, graphics::Color::new(0.2, 0.99, 0.56, 1.0))?;
-                } else {
-                    graphics::set_color(ctx, graphics::Color::new(0.73, 0.88, 0.06, 1.0))?;
+                let tile = self.get_tile([j, i]);
+                if let Some(handle) = tile.sprite() {
+                    // The diamond fills the top of the sprite, scale it to the
+                    // tile and put its middle on the tile center
+                    let sprite = assets.sprite(handle);
+                    let scale = 2.0 * self.tile_size * camera.zoom() / sprite.width;
+                    let lift = sprite.height / 2.0 - sprite.width / 4.0 - tile_set.height(tile.get_tile_type());
+
+                    let center = camera.world_to_screen(Vector2::new(x + self.tile_size / 2.0,
+                                                                     y + self.tile_size / 2.0));
+                    queue.push(depth,
+                               Layer::Ground,
+                               DrawCommand::Sprite {
+                                   sprite: handle,
+                                   dest: graphics::Point::new(center.x, center.y + lift * scale),
+                                   scale: scale,
+                               });
+                    continue;
                 }
 
-                // Create a rectangle and transform the coordinates into iso
-                let rect = vec![IsoCoord::from_cartesian(x, y).as_point(),
-                                IsoCoord::from_cartesian(x + self.tile_size, y).as_point(),
-                                IsoCoord::from_cartesian(x + self.tile_size, y + self.tile_size)
-                                    .as_point(),
-                                IsoCoord::from_cartesian(x, y + self.tile_size).as_point()]
-                    .into_boxed_slice();
+                let color = if tile.get_tile_type() == 0 {
+                    graphics::Color::new(0.2, 0.99, 0.56, 1.0)
+                } else {
+                    graphics::Color::new(0.73, 0.88, 0.06, 1.0)
+                };
 
-                graphics::polygon(ctx, DrawMode::Line, &rect)?;
+                queue.push(depth,
+                           Layer::Ground,
+                           DrawCommand::Polygon {
+                               mode: DrawMode::Line,
+                               color: color,
+                               points: self.tile_polygon(camera, x, y),
+                           });
             }
         }
-        Ok(())
+    }
+
+    /// Queue the cells of a trail on top of the board.
+    pub fn render_trail(&self, queue: &mut RenderQueue<DrawCommand>, camera: &Camera, cells: &[GridCoordinate]) {
+        for cell in cells {
+            let x = self.position.x + cell[0] as f32 * self.tile_size;
+            let y = self.position.y + cell[1] as f32 * self.tile_size;
+
+            queue.push((cell[0] + cell[1]) as f32,
+                       Layer::Trail,
+                       DrawCommand::Polygon {
+                           mode: DrawMode::Fill,
+                           color: graphics::Color::new(0.96, 0.36, 0.26, 1.0),
+                           points: self.tile_polygon(camera, x, y),
+                       });
+        }
+    }
+
+    /// Create a rectangle with its upper left corner in (x, y) and project
+    /// it onto the screen.
+    fn tile_polygon(&self, camera: &Camera, x: f32, y: f32) -> Vec<graphics::Point> {
+        vec![camera.world_to_screen(Vector2::new(x, y)),
+             camera.world_to_screen(Vector2::new(x + self.tile_size, y)),
+             camera.world_to_screen(Vector2::new(x + self.tile_size, y + self.tile_size)),
+             camera.world_to_screen(Vector2::new(x, y + self.tile_size))]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TILE: f32 = 30.0;
+
+    /// 4 by 3 board away from the world origin, seen through a moved and
+    /// zoomed camera.
+    fn board_and_camera() -> (Board, Camera) {
+        let tiles = vec![Tile::with_type(0, false); 12];
+        let board = Board::from_tiles(4, 3, TILE, Vector2::new(20.0, 10.0), tiles);
+
+        let mut camera = Camera::new(800.0, 600.0);
+        camera.look_at(Vector2::new(70.0, 35.0));
+        camera.set_zoom(2.0);
+        (board, camera)
+    }
+
+    /// Tile under the screen point showing a world point.
+    fn pick(board: &Board, camera: &Camera, x: f32, y: f32) -> Option<GridCoordinate> {
+        board.tile_at_screen(camera.world_to_screen(Vector2::new(x, y)), camera)
+    }
+
+    #[test]
+    fn edges_of_a_tile() {
+        let (board, camera) = board_and_camera();
+        // Tile [1, 2] covers x in [50, 80) and y in [70, 100)
+        let (x0, y0) = (20.0 + TILE, 10.0 + 2.0 * TILE);
+        let (x1, y1) = (x0 + TILE, y0 + TILE);
+        let (mx, my) = (x0 + TILE / 2.0, y0 + TILE / 2.0);
+        let e = 0.25;
+
+        assert_eq!(pick(&board, &camera, mx, my), Some([1, 2]));
+        assert_eq!(pick(&board, &camera, x0 + e, my), Some([1, 2]));
+        assert_eq!(pick(&board, &camera, x0 - e, my), Some([0, 2]));
+        assert_eq!(pick(&board, &camera, x1 - e, my), Some([1, 2]));
+        assert_eq!(pick(&board, &camera, x1 + e, my), Some([2, 2]));
+        assert_eq!(pick(&board, &camera, mx, y0 + e), Some([1, 2]));
+        assert_eq!(pick(&board, &camera, mx, y0 - e), Some([1, 1]));
+        assert_eq!(pick(&board, &camera, mx, y1 - e), Some([1, 2]));
+        assert_eq!(pick(&board, &camera, mx, y1 + e), None);
+    }
+
+    #[test]
+    fn corners_of_a_tile() {
+        let (board, camera) = board_and_camera();
+        // Tile [1, 1] covers x in [50, 80) and y in [40, 70)
+        let (x0, y0, x1, y1) = (50.0, 40.0, 80.0, 70.0);
+        let e = 0.25;
+
+        assert_eq!(pick(&board, &camera, x0 + e, y0 + e), Some([1, 1]));
+        assert_eq!(pick(&board, &camera, x1 - e, y0 + e), Some([1, 1]));
+        assert_eq!(pick(&board, &camera, x0 + e, y1 - e), Some([1, 1]));
+        assert_eq!(pick(&board, &camera, x1 - e, y1 - e), Some([1, 1]));
+
+        assert_eq!(pick(&board, &camera, x0 - e, y0 - e), Some([0, 0]));
+        assert_eq!(pick(&board, &camera, x1 + e, y0 - e), Some([2, 0]));
+        assert_eq!(pick(&board, &camera, x0 - e, y1 + e), Some([0, 2]));
+        assert_eq!(pick(&board, &camera, x1 + e, y1 + e), Some([2, 2]));
+    }
+
+    #[test]
+    fn outside_the_board() {
+        let (board, camera) = board_and_camera();
+        let e = 0.25;
+
+        assert_eq!(pick(&board, &camera, 20.0 + e, 10.0 + e), Some([0, 0]));
+        assert_eq!(pick(&board, &camera, 20.0 - e, 10.0 + e), None);
+        assert_eq!(pick(&board, &camera, 20.0 + e, 10.0 - e), None);
+        assert_eq!(pick(&board, &camera, 140.0 - e, 100.0 - e), Some([3, 2]));
+        assert_eq!(pick(&board, &camera, 140.0 + e, 100.0 - e), None);
+    }
+
+    /// 6 by 6 board with a safe border and a trail cutting the open middle
+    /// into one column on the left and two on the right.
+    fn cut_board() -> (Board, Vec<GridCoordinate>) {
+        let board = Board::new(6, 6, TILE, Vector2::new(0.0, 0.0));
+        let trail = vec![[2, 1], [2, 2], [2, 3], [2, 4]];
+        (board, trail)
+    }
+
+    fn open_cells(board: &Board) -> Vec<GridCoordinate> {
+        let mut cells = Vec::new();
+        for y in 0..board.height() {
+            for x in 0..board.width() {
+                if !board.is_free_move([x, y]) {
+                    cells.push([x, y]);
+                }
+            }
+        }
+        cells
+    }
+
+    #[test]
+    fn capture_fills_regions_without_enemies() {
+        let (mut board, trail) = cut_board();
+
+        let mut captured = board.capture(&trail, &[[4, 3]]);
+        captured.sort();
+        assert_eq!(captured, vec![[1, 1], [1, 2], [1, 3], [1, 4], [2, 1], [2, 2], [2, 3], [2, 4]]);
+        assert_eq!(open_cells(&board),
+                   vec![[3, 1], [4, 1], [3, 2], [4, 2], [3, 3], [4, 3], [3, 4], [4, 4]]);
+    }
+
+    #[test]
+    fn capture_keeps_regions_with_enemies() {
+        let (mut board, trail) = cut_board();
+
+        let captured = board.capture(&trail, &[[1, 4], [3, 1]]);
+        assert_eq!(captured, trail);
+        assert_eq!(open_cells(&board).len(), 12);
+
+        // Without any enemy everything closes
+        let captured = board.capture(&[], &[]);
+        assert_eq!(captured.len(), 12);
+        assert!(open_cells(&board).is_empty());
+    }
+
+    #[test]
+    fn capture_skips_safe_trail_cells() {
+        let (mut board, _) = cut_board();
+
+        let captured = board.capture(&[[0, 1], [1, 1], [9, 9]], &[[3, 3]]);
+        assert_eq!(captured, vec![[1, 1]]);
+    }
+
+    #[test]
+    fn fill_percentage_follows_captures() {
+        let (mut board, trail) = cut_board();
+        // The border is 20 of the 36 tiles
+        assert!((board.fill_percentage() - 2000.0 / 36.0).abs() < 1e-4);
+
+        board.capture(&trail, &[[4, 4]]);
+        assert!((board.fill_percentage() - 2800.0 / 36.0).abs() < 1e-4);
+
+        board.capture(&[], &[]);
+        assert_eq!(board.fill_percentage(), 100.0);
+    }
+
+    #[test]
+    fn neighbours_stop_at_every_edge() {
+        let board = Board::new(4, 3, TILE, Vector2::new(0.0, 0.0));
+
+        assert_eq!(board.get_neighbour([0, 1], GridDirection::DirectionWest), None);
+        assert_eq!(board.get_neighbour([2, 0], GridDirection::DirectionNorth), None);
+        assert_eq!(board.get_neighbour([3, 1], GridDirection::DirectionEast), None);
+        assert_eq!(board.get_neighbour([1, 2], GridDirection::DirectionSouth), None);
+        assert_eq!(board.get_neighbour([3, 2], GridDirection::DirectionEast), None);
+        assert_eq!(board.get_neighbour([3, 2], GridDirection::DirectionSouth), None);
+
+        assert_eq!(board.get_neighbour([2, 1], GridDirection::DirectionEast), Some([3, 1]));
+        assert_eq!(board.get_neighbour([1, 1], GridDirection::DirectionSouth), Some([1, 2]));
+        assert_eq!(board.get_neighbour([3, 2], GridDirection::DirectionWest), Some([2, 2]));
+        assert_eq!(board.get_neighbour([3, 2], GridDirection::DirectionNorth), Some([3, 1]));
     }
 }
@@ -9,13 +9,11 @@ pub struct IsoCoord {
 
 impl IsoCoord {
     /// Construct from coordinates in Isometric space.
-    #[allow(dead_code)]
     pub fn new(x: f32, y: f32) -> Self {
         IsoCoord { x: x, y: y }
     }
 
     /// Construct a point in Isometric space from cartesian coordinates.
-    #[allow(dead_code)]
     pub fn from_point(p: Point) -> Self {
         let iso_coords = to_iso(p);
 
@@ -39,6 +37,11 @@ impl IsoCoord {
     pub fn as_point(&self) -> Point {
         Point::new(self.x, self.y)
     }
+
+    /// Convert back to cartesian coordinates.
+    pub fn to_cartesian(&self) -> Point {
+        from_iso(self.as_point())
+    }
 }
 
 /// From cartesian coordinates to isometric coordinates.
@@ -48,3 +51,38 @@ fn to_iso(pt: Point) -> Point {
     p.y = (pt.x + pt.y) / 2.0;
     p
 }
+
+/// From isometric coordinates to cartesian coordinates, inverse of `to_iso`.
+fn from_iso(pt: Point) -> Point {
+    let mut p: Point = Point::new(0.0, 0.0);
+    p.x = pt.y + pt.x / 2.0;
+    p.y = pt.y - pt.x / 2.0;
+    p
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Point, b: Point) {
+        assert!((a.x - b.x).abs() < 1e-4 && (a.y - b.y).abs() < 1e-4, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn projects_cartesian_axes_onto_diamond() {
+        assert_close(to_iso(Point::new(10.0, 0.0)), Point::new(10.0, 5.0));
+        assert_close(to_iso(Point::new(0.0, 10.0)), Point::new(-10.0, 5.0));
+        assert_close(to_iso(Point::new(10.0, 10.0)), Point::new(0.0, 10.0));
+    }
+
+    #[test]
+    fn round_trips() {
+        let points = [(0.0, 0.0), (30.0, 0.0), (0.0, 30.0), (-12.5, 47.25), (1234.5, -987.0), (0.001, 0.002)];
+        for &(x, y) in &points {
+            let p = Point::new(x, y);
+            assert_close(from_iso(to_iso(p)), p);
+            assert_close(to_iso(from_iso(p)), p);
+            assert_close(IsoCoord::from_cartesian(x, y).to_cartesian(), p);
+        }
+    }
+}
@@ -0,0 +1,464 @@
+
+use std::io::Read;
+use std::str::FromStr;
+
+use ggez::{Context, GameError, GameResult};
+use cgmath::Vector2;
+
+use core::grid::{Board, GridCoordinate, Tile};
+use core::parse::{self, ParseError};
+
+const DEFAULT_TILE_SIZE: f32 = 30.0;
+const DEFAULT_TARGET: f32 = 75.0;
+const TILES_SECTION: &str = "[tiles]";
+
+// Characters handed out to tile types without a default legend entry
+const LEGEND_POOL: &str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum EnemyKind {
+    Bouncer,
+    Patroller,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct EnemySpawn {
+    pub kind: EnemyKind,
+    pub position: GridCoordinate,
+}
+
+/// A board with its spawns and goal, read from a plain text file of
+/// `key = value` settings followed by a `[tiles]` section holding the board
+/// as an ASCII grid, one row per line:
+///
+/// ```text
+/// # Comments start with a hash, but only above the tile grid
+/// name = Meadow
+/// tile_size = 30
+/// target = 75
+/// seed = 1337
+/// player = 0 0
+/// bouncer = 6 6
+/// patroller = 11 11
+/// legend = . 0 open
+/// legend = # 1 safe
+///
+/// [tiles]
+/// ####
+/// #..#
+/// ####
+/// ```
+///
+/// `legend` maps a character to a tile type and whether it is safe to move
+/// on. When no legend is given `.` is an open tile of type 0 and `#` a safe
+/// tile of type 1.
+#[derive(Debug, PartialEq)]
+pub struct Level {
+    pub name: String,
+    pub board: Board,
+    pub player_spawn: GridCoordinate,
+    pub enemy_spawns: Vec<EnemySpawn>,
+    /// Percentage of the board that has to be captured to win.
+    pub target_fill: f32,
+    /// Seed for enemy decisions, the game default is used when missing.
+    pub seed: Option<u64>,
+}
+
+impl Level {
+    /// Load and parse a level from the game filesystem.
+    pub fn load(ctx: &mut Context, path: &str) -> GameResult<Level> {
+        let mut file = ctx.filesystem.open(path)?;
+        let mut source = String::new();
+        file.read_to_string(&mut source)?;
+
+        Level::parse(&source).map_err(|e| GameError::ResourceLoadError(format!("{}: {}", path, e)))
+    }
+
+    /// Parse a level from its text representation.
+    pub fn parse(source: &str) -> Result<Level, ParseError> {
+        let mut name = String::new();
+        let mut tile_size = DEFAULT_TILE_SIZE;
+        let mut target_fill = DEFAULT_TARGET;
+        let mut seed = None;
+        let mut player = None;
+        let mut enemy_spawns = Vec::new();
+        let mut legend: Vec<(char, Tile)> = Vec::new();
+
+        // Spawns are validated against the board once it is read, so remember
+        // where each of them was defined.
+        let mut spawn_sites: Vec<(GridCoordinate, usize, usize)> = Vec::new();
+
+        let mut lines = source.lines().enumerate();
+        let mut tiles_line = None;
+
+        for (i, raw) in &mut lines {
+            let line_no = i + 1;
+
+            if parse::is_blank(raw) {
+                continue;
+            }
+            if raw.trim() == TILES_SECTION {
+                tiles_line = Some(line_no);
+                break;
+            }
+
+            let entry = parse::entry(line_no, raw)?;
+            let (key, value, column) = (entry.key, entry.value, entry.value_column);
+
+            match key {
+                "name" => name = value.to_string(),
+                "tile_size" => {
+                    tile_size = parse_value(value, line_no, column)?;
+                    if !tile_size.is_finite() || tile_size <= 0.0 {
+                        return Err(ParseError::at(line_no, column, "tile_size must be a positive number"));
+                    }
+                }
+                "target" => {
+                    target_fill = parse_value(value, line_no, column)?;
+                    if target_fill <= 0.0 || target_fill > 100.0 {
+                        return Err(ParseError::at(line_no, column, "target must be within (0, 100]"));
+                    }
+                }
+                "seed" => seed = Some(parse_value(value, line_no, column)?),
+                "player" => {
+                    if player.is_some() {
+                        return Err(entry.key_error("`player` defined twice"));
+                    }
+                    let position = parse_coordinate(value, line_no, column)?;
+                    player = Some(position);
+                    spawn_sites.push((position, line_no, column));
+                }
+                "bouncer" | "patroller" => {
+                    let kind = if key == "bouncer" { EnemyKind::Bouncer } else { EnemyKind::Patroller };
+                    let position = parse_coordinate(value, line_no, column)?;
+                    enemy_spawns.push(EnemySpawn {
+                        kind: kind,
+                        position: position,
+                    });
+                    spawn_sites.push((position, line_no, column));
+                }
+                "legend" => {
+                    let (c, tile) = parse_legend(value, line_no, column)?;
+                    if legend.iter().any(|&(l, _)| l == c) {
+                        return Err(ParseError::at(line_no, column, format!("legend `{}` defined twice", c)));
+                    }
+                    legend.push((c, tile));
+                }
+                _ => return Err(entry.key_error(format!("unknown key `{}`", key))),
+            }
+        }
+
+        let tiles_line = match tiles_line {
+            Some(l) => l,
+            None => return Err(ParseError::at(source.lines().count() + 1, 1, "missing `[tiles]` section")),
+        };
+
+        if legend.is_empty() {
+            legend.push(('.', Tile::with_type(0, false)));
+            legend.push(('#', Tile::with_type(1, true)));
+        }
+
+        let mut width: usize = 0;
+        let mut height: usize = 0;
+        let mut tiles = Vec::new();
+
+        for (i, raw) in lines {
+            let line_no = i + 1;
+            let row = raw.trim_right();
+            if row.is_empty() {
+                continue;
+            }
+
+            let mut row_width: usize = 0;
+            for (column, c) in row.chars().enumerate() {
+                match legend.iter().find(|&&(l, _)| l == c) {
+                    Some(&(_, tile)) => tiles.push(tile),
+                    None => return Err(ParseError::at(line_no, column + 1, format!("unknown tile `{}`", c))),
+                }
+                row_width += 1;
+            }
+
+            if height == 0 {
+                width = row_width;
+            } else if row_width != width {
+                return Err(ParseError::at(line_no,
+                                           row_width.min(width) + 1,
+                                           format!("expected a row of {} tiles, found {}", width, row_width)));
+            }
+            height += 1;
+        }
+
+        if height == 0 {
+            return Err(ParseError::at(tiles_line, 1, "the board has no tiles"));
+        }
+
+        let board = Board::from_tiles(width as u32,
+                                      height as u32,
+                                      tile_size,
+                                      Vector2::new(0.0, 0.0),
+                                      tiles);
+
+        let player_spawn = match player {
+            Some(p) => p,
+            None => return Err(ParseError::at(1, 1, "missing `player` spawn")),
+        };
+
+        for &(spawn, line_no, column) in &spawn_sites {
+            if !board.contains(spawn) {
+                return Err(ParseError::at(line_no,
+                                          column,
+                                          format!("spawn [{}, {}] is outside the board", spawn[0], spawn[1])));
+            }
+        }
+
+        Ok(Level {
+            name: name,
+            board: board,
+            player_spawn: player_spawn,
+            enemy_spawns: enemy_spawns,
+            target_fill: target_fill,
+            seed: seed,
+        })
+    }
+
+    /// Write the level in the text format `parse` reads.
+    ///
+    /// Fails when the board holds more distinct tiles than there are legend
+    /// characters, pointing at the legend line that ran out.
+    pub fn to_text(&self) -> Result<String, ParseError> {
+        let board = &self.board;
+        let mut lines = Vec::new();
+
+        if !self.name.is_empty() {
+            lines.push(format!("name = {}", self.name));
+        }
+        lines.push(format!("tile_size = {}", board.tile_size()));
+        lines.push(format!("target = {}", self.target_fill));
+        if let Some(seed) = self.seed {
+            lines.push(format!("seed = {}", seed));
+        }
+        lines.push(format!("player = {} {}", self.player_spawn[0], self.player_spawn[1]));
+        for spawn in &self.enemy_spawns {
+            let key = match spawn.kind {
+                EnemyKind::Bouncer => "bouncer",
+                EnemyKind::Patroller => "patroller",
+            };
+            lines.push(format!("{} = {} {}", key, spawn.position[0], spawn.position[1]));
+        }
+
+        let legend = build_legend(board, lines.len() + 1)?;
+        for &(c, tile) in &legend {
+            let movement = if tile.is_free_move() { "safe" } else { "open" };
+            lines.push(format!("legend = {} {} {}", c, tile.get_tile_type(), movement));
+        }
+
+        lines.push(String::new());
+        lines.push(TILES_SECTION.to_string());
+        for y in 0..board.height() {
+            let row: String = (0..board.width())
+                .map(|x| {
+                    let tile = ground_of(board.get_tile([x, y]));
+                    legend.iter().find(|&&(_, t)| t == tile).unwrap().0
+                })
+                .collect();
+            lines.push(row);
+        }
+        lines.push(String::new());
+
+        Ok(lines.join("\n"))
+    }
+}
+
+/// Give every distinct tile on the board a character, the defaults first.
+/// The legend is written starting at `first_line`, errors point there.
+fn build_legend(board: &Board, first_line: usize) -> Result<Vec<(char, Tile)>, ParseError> {
+    let mut legend: Vec<(char, Tile)> = Vec::new();
+    let mut pool = LEGEND_POOL.chars();
+
+    for y in 0..board.height() {
+        for x in 0..board.width() {
+            let tile = ground_of(board.get_tile([x, y]));
+            if legend.iter().any(|&(_, t)| t == tile) {
+                continue;
+            }
+
+            let c = if tile == Tile::with_type(0, false) {
+                '.'
+            } else if tile == Tile::with_type(1, true) {
+                '#'
+            } else {
+                match pool.next() {
+                    Some(c) => c,
+                    None => {
+                        let message = format!("no legend character left for the tile at [{}, {}]", x, y);
+                        return Err(ParseError::new(first_line + legend.len(), message));
+                    }
+                }
+            };
+            legend.push((c, tile));
+        }
+    }
+
+    Ok(legend)
+}
+
+/// Tile without its sprite, sprites are not saved with the level.
+fn ground_of(tile: &Tile) -> Tile {
+    Tile::with_type(tile.get_tile_type(), tile.is_free_move())
+}
+
+fn parse_value<T: FromStr>(value: &str, line: usize, column: usize) -> Result<T, ParseError> {
+    value.parse().map_err(|_| ParseError::at(line, column, format!("invalid value `{}`", value)))
+}
+
+fn parse_coordinate(value: &str, line: usize, column: usize) -> Result<GridCoordinate, ParseError> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    if parts.len() != 2 {
+        return Err(ParseError::at(line, column, "expected a coordinate `x y`"));
+    }
+
+    Ok([parse_value(parts[0], line, column)?, parse_value(parts[1], line, column)?])
+}
+
+fn parse_legend(value: &str, line: usize, column: usize) -> Result<(char, Tile), ParseError> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    if parts.len() != 3 || parts[0].chars().count() != 1 {
+        return Err(ParseError::at(line, column, "expected a legend `<char> <type> <safe|open>`"));
+    }
+
+    let c = parts[0].chars().next().unwrap();
+    let tile_type = parse_value(parts[1], line, column)?;
+    let free_movement = match parts[2] {
+        "safe" => true,
+        "open" => false,
+        other => return Err(ParseError::at(line, column, format!("expected `safe` or `open`, found `{}`", other))),
+    };
+
+    Ok((c, Tile::with_type(tile_type, free_movement)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(source: &str) -> (usize, Option<usize>) {
+        let e = Level::parse(source).unwrap_err();
+        (e.line, e.column)
+    }
+
+    #[test]
+    fn display_round_trips() {
+        let source = "name = Two Ponds
+tile_size = 24
+target = 62.5
+seed = 99
+player = 1 1
+bouncer = 3 2
+patroller = 4 1
+legend = . 0 open
+legend = # 1 safe
+legend = ~ 5 open
+legend = = 7 safe
+
+[tiles]
+######
+#..~=#
+#.~~.#
+######
+";
+        let level = Level::parse(source).unwrap();
+        assert_eq!(level.enemy_spawns.len(), 2);
+        assert_eq!(level.board.get_tile([3, 1]).get_tile_type(), 5);
+
+        let again = Level::parse(&level.to_text().unwrap()).unwrap();
+        assert_eq!(again, level);
+    }
+
+    #[test]
+    fn default_legend_round_trips() {
+        let level = Level::parse("player = 0 0\n[tiles]\n#.\n.#\n").unwrap();
+        assert_eq!(level.target_fill, DEFAULT_TARGET);
+        assert_eq!(Level::parse(&level.to_text().unwrap()).unwrap(), level);
+    }
+
+    /// Open level on a single row holding every tile type in a range.
+    fn level_of_types(types: ::std::ops::Range<u8>) -> Level {
+        let tiles: Vec<Tile> = types.map(|t| Tile::with_type(t, false)).collect();
+        Level {
+            name: String::new(),
+            board: Board::from_tiles(tiles.len() as u32, 1, DEFAULT_TILE_SIZE, Vector2::new(0.0, 0.0), tiles),
+            player_spawn: [0, 0],
+            enemy_spawns: Vec::new(),
+            target_fill: DEFAULT_TARGET,
+            seed: None,
+        }
+    }
+
+    #[test]
+    fn legend_pool_round_trips() {
+        // Type 0 is `.`, every other type takes a character from the pool
+        let level = level_of_types(0..LEGEND_POOL.len() as u8 + 1);
+        assert_eq!(Level::parse(&level.to_text().unwrap()).unwrap(), level);
+    }
+
+    #[test]
+    fn too_many_tiles_for_the_legend() {
+        let level = level_of_types(0..LEGEND_POOL.len() as u8 + 2);
+        let e = level.to_text().unwrap_err();
+        // Three settings, then `.` and the pool before the legend runs out
+        assert_eq!((e.line, e.column), (4 + 1 + LEGEND_POOL.len(), None));
+        assert!(e.message.contains(&format!("[{}, 0]", LEGEND_POOL.len() + 1)));
+    }
+
+    #[test]
+    fn legend_defined_twice() {
+        assert_eq!(error("player = 0 0\nlegend = . 0 open\nlegend = . 1 safe\n[tiles]\n.\n"),
+                   (3, Some(10)));
+        // The same tile under two characters is fine
+        assert!(Level::parse("player = 0 0\nlegend = . 0 open\nlegend = , 0 open\n[tiles]\n.,\n").is_ok());
+    }
+
+    #[test]
+    fn settings_errors_point_at_key_or_value() {
+        assert_eq!(error("player = 0 0\nspeed = 3\n[tiles]\n#\n"), (2, Some(1)));
+        assert_eq!(error("player = 0 0\n  target = 120\n[tiles]\n#\n"), (2, Some(12)));
+        assert_eq!(error("no equals sign\n"), (1, Some(1)));
+        assert_eq!(error("# comment\nplayer = 0\n[tiles]\n#\n"), (2, Some(10)));
+    }
+
+    #[test]
+    fn tile_size_must_be_positive() {
+        for value in &["0", "-30", "NaN", "inf"] {
+            let source = format!("player = 0 0\ntile_size = {}\n[tiles]\n#\n", value);
+            assert_eq!(error(&source), (2, Some(13)), "tile_size = {}", value);
+        }
+        assert_eq!(error("player = 0 0\ntile_size = big\n[tiles]\n#\n"), (2, Some(13)));
+    }
+
+    #[test]
+    fn spawn_errors_blame_their_own_line() {
+        // The enemy is defined before the player, it still gets the blame
+        assert_eq!(error("bouncer = 5 0\nplayer = 0 0\n[tiles]\n##\n"), (1, Some(11)));
+        assert_eq!(error("patroller = 0 0\nplayer =  0 7\n[tiles]\n##\n"), (2, Some(11)));
+        assert_eq!(error("player = 0 0\npatroller = 1 0\nbouncer = 2 2\n[tiles]\n##\n"), (3, Some(11)));
+    }
+
+    #[test]
+    fn player_defined_twice() {
+        assert_eq!(error("player = 0 0\nplayer = 1 0\n[tiles]\n##\n"), (2, Some(1)));
+    }
+
+    #[test]
+    fn missing_pieces() {
+        assert_eq!(error("[tiles]\n##\n"), (1, Some(1)));
+        assert_eq!(error("player = 0 0\n"), (2, Some(1)));
+        assert_eq!(error("player = 0 0\n[tiles]\n\n"), (2, Some(1)));
+    }
+
+    #[test]
+    fn tile_errors_point_at_the_tile() {
+        assert_eq!(error("player = 0 0\n[tiles]\n###\n#x#\n"), (4, Some(2)));
+        assert_eq!(error("player = 0 0\n[tiles]\n###\n##\n"), (4, Some(3)));
+        assert_eq!(error("player = 0 0\n[tiles]\n###\n####\n"), (4, Some(4)));
+    }
+}
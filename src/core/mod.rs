@@ -1,3 +1,16 @@
 
+pub mod animation;
+pub mod atlas;
+pub mod bindings;
+pub mod gesture;
 pub mod grid;
+pub mod handle;
 pub mod iso_coords;
+pub mod level;
+pub mod parse;
+pub mod pathfinding;
+pub mod render_queue;
+pub mod replay;
+pub mod rng;
+pub mod tileset;
+pub mod tween;
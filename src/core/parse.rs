@@ -0,0 +1,130 @@
+
+use std::error::Error;
+use std::fmt;
+use std::iter::Enumerate;
+use std::str;
+
+/// Error raised when a text file can not be parsed.
+///
+/// Lines are 1-based, and so are columns where known.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl ParseError {
+    pub fn new<S: Into<String>>(line: usize, message: S) -> Self {
+        ParseError {
+            line: line,
+            column: None,
+            message: message.into(),
+        }
+    }
+
+    pub fn at<S: Into<String>>(line: usize, column: usize, message: S) -> Self {
+        ParseError {
+            line: line,
+            column: Some(column),
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.column {
+            Some(column) => write!(f, "line {}, column {}: {}", self.line, column, self.message),
+            None => write!(f, "line {}: {}", self.line, self.message),
+        }
+    }
+}
+
+impl Error for ParseError {
+    fn description(&self) -> &str {
+        &self.message
+    }
+}
+
+/// A `key = value` line.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Entry<'a> {
+    pub line: usize,
+    pub key: &'a str,
+    pub value: &'a str,
+    pub key_column: usize,
+    /// Column of the value, or where it would start when it is empty.
+    pub value_column: usize,
+}
+
+impl<'a> Entry<'a> {
+    /// Error pointing at the value.
+    pub fn error<S: Into<String>>(&self, message: S) -> ParseError {
+        ParseError::at(self.line, self.value_column, message)
+    }
+
+    /// Error pointing at the key.
+    pub fn key_error<S: Into<String>>(&self, message: S) -> ParseError {
+        ParseError::at(self.line, self.key_column, message)
+    }
+}
+
+/// Whether a line holds nothing to parse.
+pub fn is_blank(line: &str) -> bool {
+    let line = line.trim();
+    line.is_empty() || line.starts_with('#')
+}
+
+/// Split a line into its key and value.
+pub fn entry(line: usize, raw: &str) -> Result<Entry, ParseError> {
+    let eq = match raw.find('=') {
+        Some(eq) => eq,
+        None => return Err(ParseError::at(line, column_of(raw, raw.trim()), "expected `key = value`")),
+    };
+
+    let key = raw[..eq].trim();
+    let value = raw[eq + 1..].trim();
+
+    Ok(Entry {
+        line: line,
+        key: key,
+        value: value,
+        key_column: column_of(raw, key),
+        value_column: if value.is_empty() { eq + 2 } else { column_of(raw, value) },
+    })
+}
+
+/// Every `key = value` line of a source, skipping blank lines and comments
+/// starting with `#`. Levels, bindings, atlases, tile sets and clips all use
+/// this layout.
+pub fn entries(source: &str) -> Entries {
+    Entries { lines: source.lines().enumerate() }
+}
+
+pub struct Entries<'a> {
+    lines: Enumerate<str::Lines<'a>>,
+}
+
+impl<'a> Iterator for Entries<'a> {
+    type Item = Result<Entry<'a>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for (i, raw) in &mut self.lines {
+            if !is_blank(raw) {
+                return Some(entry(i + 1, raw));
+            }
+        }
+        None
+    }
+}
+
+/// 1-based column of a slice taken from a line.
+fn column_of(line: &str, part: &str) -> usize {
+    part.as_ptr() as usize - line.as_ptr() as usize + 1
+}
+
+/// Line to blame for something missing from a whole source.
+pub fn last_line(source: &str) -> usize {
+    source.lines().count().max(1)
+}
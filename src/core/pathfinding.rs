@@ -0,0 +1,454 @@
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+
+use core::grid::{self, Board, GridCoordinate, GridDirection};
+
+/// Cost function only allowing safe ground, at a cost of one per tile.
+pub fn safe_tiles(board: &Board, coord: GridCoordinate) -> Option<u32> {
+    if board.is_free_move(coord) { Some(1) } else { None }
+}
+
+/// Cost function only allowing open ground, at a cost of one per tile.
+pub fn open_tiles(board: &Board, coord: GridCoordinate) -> Option<u32> {
+    if board.contains(coord) && !board.is_free_move(coord) { Some(1) } else { None }
+}
+
+/// Open list entry, ordered by estimated total cost, then by the estimate
+/// left so paths closer to the goal are tried first, then by tile so equal
+/// boards always give equal paths.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Node {
+    estimate: u32,
+    remaining: u32,
+    index: usize,
+}
+
+impl Ord for Node {
+    fn cmp(&self, other: &Node) -> Ordering {
+        (self.estimate, self.remaining, self.index).cmp(&(other.estimate, other.remaining, other.index))
+    }
+}
+
+impl PartialOrd for Node {
+    fn partial_cmp(&self, other: &Node) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Per tile bookkeeping shared by the searches.
+///
+/// Entries are only valid when their stamp matches the current generation,
+/// which saves clearing the buffers before every search. Buffers only grow
+/// when a bigger board comes along, so searching every tick does not
+/// allocate.
+#[derive(Debug)]
+struct SearchState {
+    width: u32,
+    height: u32,
+    cost: Vec<u32>,
+    came_from: Vec<usize>,
+    stamp: Vec<u32>,
+    generation: u32,
+    open: BinaryHeap<Reverse<Node>>,
+}
+
+impl SearchState {
+    fn new() -> Self {
+        SearchState {
+            width: 0,
+            height: 0,
+            cost: Vec::new(),
+            came_from: Vec::new(),
+            stamp: Vec::new(),
+            generation: 0,
+            open: BinaryHeap::new(),
+        }
+    }
+
+    /// Invalidate everything from the last search and fit the buffers to a
+    /// board.
+    fn reset(&mut self, board: &Board) {
+        let size = (board.width() * board.height()) as usize;
+        self.width = board.width();
+        self.height = board.height();
+
+        if self.stamp.len() < size {
+            self.cost.resize(size, 0);
+            self.came_from.resize(size, 0);
+            self.stamp.resize(size, 0);
+        }
+
+        self.generation = self.generation.wrapping_add(1);
+        if self.generation == 0 {
+            // Old stamps could look current again after wrapping around
+            for stamp in &mut self.stamp {
+                *stamp = 0;
+            }
+            self.generation = 1;
+        }
+
+        self.open.clear();
+    }
+
+    fn index(&self, coord: GridCoordinate) -> usize {
+        (coord[0] + self.width * coord[1]) as usize
+    }
+
+    fn coord(&self, index: usize) -> GridCoordinate {
+        let index = index as u32;
+        [index % self.width, index / self.width]
+    }
+
+    fn cost(&self, index: usize) -> Option<u32> {
+        if self.stamp[index] == self.generation { Some(self.cost[index]) } else { None }
+    }
+
+    fn set(&mut self, index: usize, cost: u32, came_from: usize) {
+        self.stamp[index] = self.generation;
+        self.cost[index] = cost;
+        self.came_from[index] = came_from;
+    }
+}
+
+/// A* search between two tiles.
+///
+/// Searches never decide on their own which tiles can be walked on, callers
+/// pass a cost function returning the cost of entering a tile, or `None` when
+/// it can not be entered at all. `safe_tiles` and `open_tiles` cover the
+/// common cases.
+#[derive(Debug)]
+pub struct PathFinder {
+    state: SearchState,
+}
+
+impl PathFinder {
+    pub fn new() -> Self {
+        PathFinder { state: SearchState::new() }
+    }
+
+    /// Find the cheapest path from `start` to `goal`, written to `path`
+    /// without the start tile. Returns false and leaves `path` empty when the
+    /// goal can not be reached.
+    ///
+    /// The start tile is never passed to `cost`. Costs below one are counted
+    /// as one, which keeps the distance estimate from overshooting.
+    pub fn find_path<F>(&mut self,
+                        board: &Board,
+                        start: GridCoordinate,
+                        goal: GridCoordinate,
+                        mut cost: F,
+                        path: &mut Vec<GridCoordinate>)
+                        -> bool
+        where F: FnMut(GridCoordinate) -> Option<u32>
+    {
+        path.clear();
+        if !board.contains(start) || !board.contains(goal) {
+            return false;
+        }
+
+        let state = &mut self.state;
+        state.reset(board);
+
+        let start_index = state.index(start);
+        let goal_index = state.index(goal);
+        state.set(start_index, 0, start_index);
+        state.open.push(Reverse(Node {
+            estimate: grid::manhattan_distance(start, goal),
+            remaining: grid::manhattan_distance(start, goal),
+            index: start_index,
+        }));
+
+        while let Some(Reverse(node)) = state.open.pop() {
+            if node.index == goal_index {
+                break;
+            }
+
+            let current_cost = state.cost[node.index];
+            let current = state.coord(node.index);
+
+            // Skip entries superseded by a cheaper route
+            if node.estimate - node.remaining > current_cost {
+                continue;
+            }
+
+            for &direction in &grid::ALL_DIRECTIONS {
+                let next = match board.get_neighbour(current, direction) {
+                    Some(next) => next,
+                    None => continue,
+                };
+                let step = match cost(next) {
+                    Some(step) => step.max(1),
+                    None => continue,
+                };
+
+                let next_index = state.index(next);
+                let next_cost = current_cost + step;
+                if state.cost(next_index).map_or(true, |c| next_cost < c) {
+                    state.set(next_index, next_cost, node.index);
+                    let remaining = grid::manhattan_distance(next, goal);
+                    state.open.push(Reverse(Node {
+                        estimate: next_cost + remaining,
+                        remaining: remaining,
+                        index: next_index,
+                    }));
+                }
+            }
+        }
+
+        if state.cost(goal_index).is_none() || goal_index == start_index {
+            return false;
+        }
+
+        let mut index = goal_index;
+        while index != start_index {
+            path.push(state.coord(index));
+            index = state.came_from[index];
+        }
+        path.reverse();
+        true
+    }
+}
+
+/// Cost of reaching the nearest goal from every tile, found with a breadth
+/// first search that weighs tiles by their cost.
+///
+/// Any number of entities can then walk downhill to the goals without
+/// searching on their own.
+#[derive(Debug)]
+pub struct FlowField {
+    state: SearchState,
+}
+
+impl FlowField {
+    pub fn new() -> Self {
+        FlowField { state: SearchState::new() }
+    }
+
+    /// Spread out from the goals. Goals are reached at no cost even when
+    /// `cost` would not allow entering them.
+    pub fn build<F>(&mut self, board: &Board, goals: &[GridCoordinate], mut cost: F)
+        where F: FnMut(GridCoordinate) -> Option<u32>
+    {
+        let state = &mut self.state;
+        state.reset(board);
+
+        for &goal in goals.iter().filter(|&&g| board.contains(g)) {
+            let index = state.index(goal);
+            state.set(index, 0, index);
+            state.open.push(Reverse(Node {
+                estimate: 0,
+                remaining: 0,
+                index: index,
+            }));
+        }
+
+        while let Some(Reverse(node)) = state.open.pop() {
+            let current_cost = state.cost[node.index];
+            if node.estimate > current_cost {
+                continue;
+            }
+            let current = state.coord(node.index);
+
+            // Only spread to tiles that can be stood on, weighed by their own
+            // cost
+            for &direction in &grid::ALL_DIRECTIONS {
+                let next = match board.get_neighbour(current, direction) {
+                    Some(next) => next,
+                    None => continue,
+                };
+                let step = match cost(next) {
+                    Some(step) => step.max(1),
+                    None => continue,
+                };
+
+                let next_index = state.index(next);
+                let next_cost = current_cost + step;
+                if state.cost(next_index).map_or(true, |c| next_cost < c) {
+                    state.set(next_index, next_cost, node.index);
+                    state.open.push(Reverse(Node {
+                        estimate: next_cost,
+                        remaining: 0,
+                        index: next_index,
+                    }));
+                }
+            }
+        }
+    }
+
+    /// Cost from a tile to the nearest goal, none if no goal can be reached.
+    pub fn distance(&self, coord: GridCoordinate) -> Option<u32> {
+        if coord[0] >= self.state.width || coord[1] >= self.state.height {
+            return None;
+        }
+        self.state.cost(self.state.index(coord))
+    }
+
+    /// Direction of the next step towards the nearest goal.
+    pub fn direction(&self, coord: GridCoordinate) -> Option<GridDirection> {
+        let here = self.distance(coord)?;
+        if here == 0 {
+            return None;
+        }
+
+        let next = self.state.coord(self.state.came_from[self.state.index(coord)]);
+        let direction = if next[1] < coord[1] {
+            GridDirection::DirectionNorth
+        } else if next[1] > coord[1] {
+            GridDirection::DirectionSouth
+        } else if next[0] > coord[0] {
+            GridDirection::DirectionEast
+        } else {
+            GridDirection::DirectionWest
+        };
+        Some(direction)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cgmath::Vector2;
+
+    use core::grid::Tile;
+
+    /// Board from rows of text, `#` is safe ground and anything else open.
+    fn board(rows: &[&str]) -> Board {
+        let tiles = rows.iter()
+            .flat_map(|row| row.chars())
+            .map(|c| if c == '#' { Tile::with_type(1, true) } else { Tile::with_type(0, false) })
+            .collect();
+        Board::from_tiles(rows[0].len() as u32, rows.len() as u32, 30.0, Vector2::new(0.0, 0.0), tiles)
+    }
+
+    fn search(finder: &mut PathFinder,
+              board: &Board,
+              start: GridCoordinate,
+              goal: GridCoordinate)
+              -> Option<Vec<GridCoordinate>> {
+        let mut path = vec![[99, 99]];
+        if finder.find_path(board, start, goal, |c| open_tiles(board, c), &mut path) {
+            Some(path)
+        } else {
+            assert!(path.is_empty());
+            None
+        }
+    }
+
+    /// Check every step moves one tile onto open ground and the path ends at
+    /// the goal.
+    fn assert_walkable(board: &Board, start: GridCoordinate, goal: GridCoordinate, path: &[GridCoordinate]) {
+        let mut previous = start;
+        for &tile in path {
+            assert_eq!(grid::manhattan_distance(previous, tile), 1, "{:?} to {:?}", previous, tile);
+            assert!(!board.is_free_move(tile), "{:?} is a wall", tile);
+            previous = tile;
+        }
+        assert_eq!(previous, goal);
+    }
+
+    #[test]
+    fn path_goes_around_a_wall() {
+        let board = board(&[".....",
+                            "..#..",
+                            "..#..",
+                            "..#..",
+                            "....."]);
+        let mut finder = PathFinder::new();
+
+        let path = search(&mut finder, &board, [0, 2], [4, 2]).unwrap();
+        assert_eq!(path.len(), 8);
+        assert_walkable(&board, [0, 2], [4, 2], &path);
+    }
+
+    #[test]
+    fn path_leaves_out_the_start() {
+        let board = board(&["...", "...", "..."]);
+        let mut finder = PathFinder::new();
+
+        assert_eq!(search(&mut finder, &board, [1, 1], [2, 1]), Some(vec![[2, 1]]));
+        assert_eq!(search(&mut finder, &board, [0, 0], [0, 2]), Some(vec![[0, 1], [0, 2]]));
+        assert_eq!(search(&mut finder, &board, [1, 1], [1, 1]), None);
+    }
+
+    #[test]
+    fn unreachable_goal() {
+        let board = board(&[".....",
+                            ".###.",
+                            ".#.#.",
+                            ".###.",
+                            "....."]);
+        let mut finder = PathFinder::new();
+
+        assert_eq!(search(&mut finder, &board, [0, 0], [2, 2]), None);
+        assert_eq!(search(&mut finder, &board, [0, 0], [1, 1]), None);
+        assert_eq!(search(&mut finder, &board, [0, 0], [5, 0]), None);
+        // The failed searches leave nothing behind for the next one
+        assert!(search(&mut finder, &board, [0, 0], [4, 4]).is_some());
+    }
+
+    #[test]
+    fn flow_field_towards_nearest_goal() {
+        let board = board(&[".....",
+                            ".....",
+                            ".....",
+                            ".....",
+                            "....#"]);
+        let mut field = FlowField::new();
+        field.build(&board, &[[0, 0], [4, 3]], |c| open_tiles(&board, c));
+
+        assert_eq!(field.distance([0, 0]), Some(0));
+        assert_eq!(field.distance([4, 3]), Some(0));
+        assert_eq!(field.distance([1, 1]), Some(2));
+        assert_eq!(field.distance([3, 4]), Some(2));
+        assert_eq!(field.distance([2, 2]), Some(3));
+        assert_eq!(field.distance([4, 4]), None);
+        assert_eq!(field.distance([5, 0]), None);
+
+        assert_eq!(field.direction([0, 0]), None);
+        assert_eq!(field.direction([4, 4]), None);
+        assert_eq!(field.direction([0, 2]), Some(GridDirection::DirectionNorth));
+        assert_eq!(field.direction([2, 0]), Some(GridDirection::DirectionWest));
+        assert_eq!(field.direction([4, 1]), Some(GridDirection::DirectionSouth));
+        assert_eq!(field.direction([2, 3]), Some(GridDirection::DirectionEast));
+
+        // Following the directions walks down to a goal
+        let mut tile = [0, 4];
+        let mut steps = 0;
+        while let Some(direction) = field.direction(tile) {
+            tile = board.get_neighbour(tile, direction).unwrap();
+            steps += 1;
+        }
+        assert_eq!(field.distance(tile), Some(0));
+        assert_eq!(steps, 4);
+    }
+
+    #[test]
+    fn buffers_follow_the_board_size() {
+        let small = board(&["...", "...", "..."]);
+        let wide = board(&["........",
+                           ".######.",
+                           "........"]);
+        let mut finder = PathFinder::new();
+        let mut field = FlowField::new();
+
+        assert_eq!(search(&mut finder, &small, [0, 0], [2, 2]).map(|p| p.len()), Some(4));
+        field.build(&small, &[[0, 0]], |c| open_tiles(&small, c));
+        assert_eq!(field.distance([2, 2]), Some(4));
+
+        let path = search(&mut finder, &wide, [0, 1], [7, 1]).unwrap();
+        assert_eq!(path.len(), 9);
+        assert_walkable(&wide, [0, 1], [7, 1], &path);
+        field.build(&wide, &[[0, 0]], |c| open_tiles(&wide, c));
+        assert_eq!(field.distance([7, 2]), Some(9));
+        assert_eq!(field.distance([3, 1]), None);
+
+        // Back on the smaller board the larger buffers are reused
+        assert_eq!(search(&mut finder, &small, [2, 0], [0, 2]).map(|p| p.len()), Some(4));
+        field.build(&small, &[[2, 2]], |c| open_tiles(&small, c));
+        assert_eq!(field.distance([0, 0]), Some(4));
+        assert_eq!(field.distance([3, 0]), None);
+        assert_eq!(field.distance([0, 3]), None);
+    }
+}
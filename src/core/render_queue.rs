@@ -0,0 +1,179 @@
+
+use std::cmp::Ordering;
+
+use ggez::{Context, GameResult, graphics};
+use ggez::graphics::{Color, DrawMode, Point};
+
+use core::handle::Handle;
+use resources::assets::{Assets, Sprite};
+
+/// What kind of thing is drawn, later layers cover earlier ones at the same
+/// depth.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Layer {
+    Ground,
+    Trail,
+    Entity,
+    Effect,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SortKey {
+    pub depth: f32,
+    pub layer: Layer,
+    // Push order, keeps the sort stable
+    order: usize,
+}
+
+impl PartialEq for SortKey {
+    fn eq(&self, other: &SortKey) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for SortKey {}
+
+impl PartialOrd for SortKey {
+    fn partial_cmp(&self, other: &SortKey) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SortKey {
+    fn cmp(&self, other: &SortKey) -> Ordering {
+        self.depth
+            .partial_cmp(&other.depth)
+            .unwrap_or(Ordering::Equal)
+            .then(self.layer.cmp(&other.layer))
+            .then(self.order.cmp(&other.order))
+    }
+}
+
+/// Collects items for a frame and hands them back sorted back-to-front.
+///
+/// Items are pushed in any order with a depth, the sum of their row and
+/// column on the board, and a layer. Lower depths are drawn first, items at
+/// the same depth by layer and then in the order they were pushed.
+#[derive(Debug)]
+pub struct RenderQueue<T> {
+    items: Vec<(SortKey, T)>,
+}
+
+impl<T> RenderQueue<T> {
+    pub fn new() -> Self {
+        RenderQueue { items: Vec::new() }
+    }
+
+    pub fn push(&mut self, depth: f32, layer: Layer, item: T) {
+        let key = SortKey {
+            depth: depth,
+            layer: layer,
+            order: self.items.len(),
+        };
+        self.items.push((key, item));
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Take every item out of the queue in draw order, the queue keeps its
+    /// allocation for the next frame.
+    pub fn drain_sorted(&mut self) -> ::std::vec::Drain<(SortKey, T)> {
+        self.items.sort_by(|a, b| a.0.cmp(&b.0));
+        self.items.drain(..)
+    }
+}
+
+/// Something to draw on the board.
+#[derive(Debug, Clone)]
+pub enum DrawCommand {
+    /// Sprite centered on a screen point.
+    Sprite {
+        sprite: Handle<Sprite>,
+        dest: Point,
+        scale: f32,
+    },
+    Polygon {
+        mode: DrawMode,
+        color: Color,
+        points: Vec<Point>,
+    },
+}
+
+impl RenderQueue<DrawCommand> {
+    /// Draw and empty the queue.
+    pub fn submit(&mut self, ctx: &mut Context, assets: &Assets) -> GameResult<()> {
+        for (_, command) in self.drain_sorted() {
+            match command {
+                DrawCommand::Sprite { sprite, dest, scale } => {
+                    graphics::set_color(ctx, Color::new(1.0, 1.0, 1.0, 1.0))?;
+                    assets.draw_sprite(ctx, sprite, dest, scale)?;
+                }
+                DrawCommand::Polygon { mode, color, points } => {
+                    graphics::set_color(ctx, color)?;
+                    graphics::polygon(ctx, mode, &points)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn drained(queue: &mut RenderQueue<&'static str>) -> Vec<&'static str> {
+        queue.drain_sorted().map(|(_, item)| item).collect()
+    }
+
+    #[test]
+    fn lower_depth_is_drawn_first() {
+        let mut queue = RenderQueue::new();
+        queue.push(4.0, Layer::Ground, "far");
+        queue.push(1.5, Layer::Ground, "near");
+        queue.push(2.0, Layer::Entity, "middle");
+        queue.push(0.0, Layer::Effect, "front");
+        assert_eq!(drained(&mut queue), vec!["front", "near", "middle", "far"]);
+    }
+
+    #[test]
+    fn layer_breaks_depth_ties() {
+        let mut queue = RenderQueue::new();
+        queue.push(3.0, Layer::Effect, "effect");
+        queue.push(3.0, Layer::Entity, "entity");
+        queue.push(3.0, Layer::Trail, "trail");
+        queue.push(3.0, Layer::Ground, "ground");
+        queue.push(2.0, Layer::Effect, "effect behind");
+        assert_eq!(drained(&mut queue), vec!["effect behind", "ground", "trail", "entity", "effect"]);
+    }
+
+    #[test]
+    fn equal_keys_keep_push_order() {
+        let mut queue = RenderQueue::new();
+        for &item in &["a", "b", "c", "d", "e"] {
+            queue.push(1.0, Layer::Entity, item);
+        }
+        queue.push(0.0, Layer::Entity, "z");
+        assert_eq!(drained(&mut queue), vec!["z", "a", "b", "c", "d", "e"]);
+    }
+
+    #[test]
+    fn draining_empties_the_queue() {
+        let mut queue = RenderQueue::new();
+        queue.push(1.0, Layer::Ground, "a");
+        queue.push(0.0, Layer::Ground, "b");
+        assert_eq!(drained(&mut queue), vec!["b", "a"]);
+        assert!(queue.is_empty());
+
+        // Push order starts over for the next frame
+        queue.push(1.0, Layer::Ground, "c");
+        queue.push(1.0, Layer::Ground, "d");
+        assert_eq!(drained(&mut queue), vec!["c", "d"]);
+    }
+}
@@ -0,0 +1,299 @@
+use std::fmt;
+
+use ggez::event::{Axis, Button, Keycode};
+
+use components::input::TouchCommand;
+use core::bindings::Bindings;
+use core::parse::ParseError;
+
+const MAGIC: &str = "robofill-replay";
+
+const BINDING_PREFIX: &str = "binding ";
+
+/// Version of the format, the one written by `Display`.
+pub const VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReplayFrame {
+    pub delta: f32,
+    pub pressed: Vec<Keycode>,
+    pub buttons: Vec<Button>,
+    pub axes: Vec<(Axis, f32)>,
+    pub touches: Vec<TouchCommand>,
+}
+
+/// A recorded input session.
+///
+/// Stores the level it started on, the bindings it was played with and, for
+/// every simulation step, the time step and the state of every input device.
+/// Feeding the frames back through the same systems reproduces the session
+/// exactly.
+///
+/// ```text
+/// robofill-replay 1
+/// level /levels/01.lvl
+/// binding dead_zone = 0.35
+/// binding policy = most_recent
+/// binding up = key:W, key:Up, button:dpup, axis:lefty-
+/// frame 3c888889 26 - - -
+/// frame 3c888889 - dpup leftx=3f000000 -
+/// frame 3c888889 - - - up
+/// frame 3c888889 - - - goto:4:7
+/// ```
+///
+/// Time steps and axis values are stored as the bits of the `f32` so they
+/// survive the round trip untouched. Keys are SDL keycodes, oldest press
+/// first. Buttons and axes use the SDL controller names, touches are the
+/// actions swipes turned into or the tiles tapped, `-` means nothing is held.
+/// The `binding` lines hold the bindings file of the session.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Replay {
+    pub level: String,
+    pub bindings: Bindings,
+    pub frames: Vec<ReplayFrame>,
+}
+
+impl Replay {
+    pub fn new(level: &str, bindings: Bindings) -> Self {
+        Replay {
+            level: level.to_string(),
+            bindings: bindings,
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn parse(source: &str) -> Result<Replay, ParseError> {
+        let mut lines = source.lines().enumerate().filter(|&(_, l)| !l.trim().is_empty()).peekable();
+
+        match lines.next() {
+            Some((i, header)) => {
+                let parts: Vec<&str> = header.split_whitespace().collect();
+                if parts.len() != 2 || parts[0] != MAGIC {
+                    return Err(ParseError::new(i + 1, "not a replay file"));
+                }
+                if parts[1] != VERSION.to_string() {
+                    return Err(ParseError::new(i + 1, format!("unsupported version `{}`", parts[1])));
+                }
+            }
+            None => return Err(ParseError::new(1, "empty replay")),
+        }
+
+        let (level_line, level) = match lines.next() {
+            Some((i, line)) if line.starts_with("level ") => (i + 1, line["level ".len()..].trim().to_string()),
+            Some((i, _)) => return Err(ParseError::new(i + 1, "expected `level <path>`")),
+            None => return Err(ParseError::new(2, "missing level")),
+        };
+
+        // Remember where each binding line was so errors point into the
+        // replay and not the bindings
+        let mut binding_lines = Vec::new();
+        while let Some(&(i, line)) = lines.peek() {
+            if !line.starts_with(BINDING_PREFIX) {
+                break;
+            }
+            binding_lines.push((i + 1, &line[BINDING_PREFIX.len()..]));
+            lines.next();
+        }
+        if binding_lines.is_empty() {
+            return Err(ParseError::new(level_line + 1, "missing bindings"));
+        }
+
+        let bindings_source: Vec<&str> = binding_lines.iter().map(|&(_, l)| l).collect();
+        let bindings = Bindings::parse(&bindings_source.join("\n")).map_err(|e| {
+            ParseError {
+                line: binding_lines[e.line - 1].0,
+                column: e.column.map(|c| c + BINDING_PREFIX.len()),
+                message: e.message,
+            }
+        })?;
+
+        let mut frames = Vec::new();
+        for (i, line) in lines {
+            frames.push(parse_frame(line).map_err(|m| ParseError::new(i + 1, m))?);
+        }
+
+        Ok(Replay {
+            level: level,
+            bindings: bindings,
+            frames: frames,
+        })
+    }
+}
+
+impl fmt::Display for Replay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} {}", MAGIC, VERSION)?;
+        writeln!(f, "level {}", self.level)?;
+        for line in self.bindings.to_string().lines() {
+            writeln!(f, "{}{}", BINDING_PREFIX, line)?;
+        }
+
+        for frame in &self.frames {
+            let keys: Vec<String> = frame.pressed.iter().map(|&key| (key as i32).to_string()).collect();
+            let buttons: Vec<String> = frame.buttons.iter().map(|&btn| btn.string()).collect();
+            let axes: Vec<String> = frame.axes
+                .iter()
+                .map(|&(axis, value)| format!("{}={:08x}", axis.string(), value.to_bits()))
+                .collect();
+            let touches: Vec<String> = frame.touches.iter().map(|t| t.name()).collect();
+
+            writeln!(f,
+                     "frame {:08x} {} {} {} {}",
+                     frame.delta.to_bits(),
+                     column(&keys),
+                     column(&buttons),
+                     column(&axes),
+                     column(&touches))?;
+        }
+        Ok(())
+    }
+}
+
+/// Comma separated list, `-` when empty.
+fn column(items: &[String]) -> String {
+    if items.is_empty() {
+        "-".to_string()
+    } else {
+        items.join(",")
+    }
+}
+
+/// Items of a comma separated list, none for `-`.
+fn items(column: &str) -> Vec<&str> {
+    if column == "-" {
+        Vec::new()
+    } else {
+        column.split(',').collect()
+    }
+}
+
+fn parse_frame(line: &str) -> Result<ReplayFrame, String> {
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    if parts.len() != 6 || parts[0] != "frame" {
+        return Err("expected `frame <delta> <keys> <buttons> <axes> <touches>`".to_string());
+    }
+
+    let delta = u32::from_str_radix(parts[1], 16)
+        .map(f32::from_bits)
+        .map_err(|_| format!("invalid time step `{}`", parts[1]))?;
+
+    let mut pressed = Vec::new();
+    for key in items(parts[2]) {
+        let code = key.parse::<i32>().ok().and_then(Keycode::from_i32);
+        match code {
+            Some(code) => pressed.push(code),
+            None => return Err(format!("invalid key `{}`", key)),
+        }
+    }
+
+    let mut buttons = Vec::new();
+    for name in items(parts[3]) {
+        match Button::from_string(name) {
+            Some(btn) => buttons.push(btn),
+            None => return Err(format!("invalid button `{}`", name)),
+        }
+    }
+
+    let mut axes = Vec::new();
+    for entry in items(parts[4]) {
+        let mut split = entry.splitn(2, '=');
+        let axis = split.next().and_then(Axis::from_string);
+        let value = split.next()
+            .and_then(|bits| u32::from_str_radix(bits, 16).ok())
+            .map(f32::from_bits);
+        match (axis, value) {
+            (Some(axis), Some(value)) => axes.push((axis, value)),
+            _ => return Err(format!("invalid axis `{}`", entry)),
+        }
+    }
+
+    let mut touches = Vec::new();
+    for name in items(parts[5]) {
+        match TouchCommand::from_name(name) {
+            Some(command) => touches.push(command),
+            None => return Err(format!("invalid touch `{}`", name)),
+        }
+    }
+
+    Ok(ReplayFrame {
+        delta: delta,
+        pressed: pressed,
+        buttons: buttons,
+        axes: axes,
+        touches: touches,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use components::input::InputAction;
+
+    fn frame(delta: f32, pressed: Vec<Keycode>) -> ReplayFrame {
+        ReplayFrame {
+            delta: delta,
+            pressed: pressed,
+            buttons: Vec::new(),
+            axes: Vec::new(),
+            touches: Vec::new(),
+        }
+    }
+
+    fn sample() -> Replay {
+        let mut replay = Replay::new("/levels/01.lvl", Bindings::default());
+        replay.frames.push(frame(1.0 / 60.0, vec![Keycode::D]));
+        replay.frames.push(frame(0.1, vec![Keycode::S, Keycode::Left]));
+        replay.frames.push(ReplayFrame {
+            delta: 1.0 / 60.0,
+            pressed: Vec::new(),
+            buttons: vec![Button::A, Button::DPadUp],
+            axes: vec![(Axis::LeftX, -0.123_456_79), (Axis::LeftY, 1.0)],
+            touches: vec![TouchCommand::Action(InputAction::MoveUp), TouchCommand::Goto([4, 7])],
+        });
+        replay.frames.push(frame(::std::f32::MIN_POSITIVE, Vec::new()));
+        replay
+    }
+
+    #[test]
+    fn display_round_trips() {
+        let replay = sample();
+        let text = replay.to_string();
+        assert!(text.starts_with("robofill-replay 1\nlevel /levels/01.lvl\nbinding "));
+        assert_eq!(Replay::parse(&text).unwrap(), replay);
+    }
+
+    #[test]
+    fn time_steps_keep_their_bits() {
+        let parsed = Replay::parse(&sample().to_string()).unwrap();
+        for (a, b) in parsed.frames.iter().zip(sample().frames.iter()) {
+            assert_eq!(a.delta.to_bits(), b.delta.to_bits());
+        }
+    }
+
+    #[test]
+    fn header_errors() {
+        assert_eq!(Replay::parse("").unwrap_err().line, 1);
+        assert_eq!(Replay::parse("robofill-replay 2\n").unwrap_err().line, 1);
+        assert_eq!(Replay::parse("something else\n").unwrap_err().line, 1);
+        assert_eq!(Replay::parse("robofill-replay 1\nframe 0 - - - -\n").unwrap_err().line, 2);
+
+        let err = Replay::parse("robofill-replay 1\nlevel /a.lvl\nframe 3c888889 - - - -\n").unwrap_err();
+        assert_eq!((err.line, err.message.as_str()), (3, "missing bindings"));
+    }
+
+    #[test]
+    fn errors_point_at_replay_lines() {
+        let err = Replay::parse("robofill-replay 1\nlevel /a.lvl\nbinding policy = most_recent\nbinding jump = key:W\n")
+            .unwrap_err();
+        assert_eq!((err.line, err.column), (4, Some(9)));
+
+        let mut text = sample().to_string();
+        text.push_str("frame 3c888889 26 - -\n");
+        let lines = text.lines().count();
+        assert_eq!(Replay::parse(&text).unwrap_err().line, lines);
+
+        let text = sample().to_string().replace("goto:4:7", "goto:4");
+        assert!(Replay::parse(&text).unwrap_err().message.contains("goto:4"));
+    }
+}
@@ -0,0 +1,344 @@
+
+use std::io::Read;
+
+use ggez::{Context, GameError, GameResult};
+
+use core::grid::{Board, GridCoordinate, GridDirection};
+use core::handle::Handle;
+use core::parse::{self, Entry, ParseError};
+use core::rng::Rng;
+use resources::assets::{Assets, Sprite};
+
+/// Regions an autotiled type needs, one per neighbour combination.
+const AUTOTILE_VARIANTS: usize = 16;
+
+/// How a tile picks one of the sprites of its type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VariantRule {
+    Random,
+    Autotile,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileStyleSource {
+    pub kind: u8,
+    pub rule: VariantRule,
+    pub regions: Vec<String>,
+}
+
+/// Tile set file, pointing at an atlas and listing per tile type the regions
+/// it is drawn with:
+///
+/// ```text
+/// atlas = /tiles/grass.atlas
+/// tile = 0 random grass_0 grass_1 grass_2
+/// tile = 1 autotile edge_0 edge_1 ... edge_15
+/// height = 1 4
+/// ```
+///
+/// `random` picks one of the regions per tile, the choice only depends on the
+/// tile coordinate so it stays put between frames. `autotile` takes exactly
+/// 16 regions indexed by which neighbours share the tile type, north adds 1,
+/// east 2, south 4 and west 8. `height` raises a tile type by a number of
+/// atlas pixels. Types without a `tile` line are drawn as outlines.
+///
+/// Regions hold the tile diamond across their top, twice as wide as it is
+/// high, anything below the diamond is the side of the tile.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TileSetSource {
+    pub atlas: String,
+    pub styles: Vec<TileStyleSource>,
+    pub heights: Vec<(u8, f32)>,
+}
+
+impl TileSetSource {
+    pub fn parse(source: &str) -> Result<TileSetSource, ParseError> {
+        let mut atlas = None;
+        let mut styles: Vec<TileStyleSource> = Vec::new();
+        let mut heights: Vec<(u8, f32)> = Vec::new();
+
+        for entry in parse::entries(source) {
+            let entry = entry?;
+            let (key, value) = (entry.key, entry.value);
+            let parts: Vec<&str> = value.split_whitespace().collect();
+
+            match key {
+                "atlas" => atlas = Some(value.to_string()),
+                "tile" => {
+                    if parts.len() < 3 {
+                        return Err(entry.error("expected `<type> <random|autotile> <region>...`"));
+                    }
+
+                    let kind = parse_kind(parts[0], &entry)?;
+                    if styles.iter().any(|s| s.kind == kind) {
+                        return Err(entry.error(format!("tile type {} defined twice", kind)));
+                    }
+
+                    let rule = match parts[1] {
+                        "random" => VariantRule::Random,
+                        "autotile" => VariantRule::Autotile,
+                        other => return Err(entry.error(format!("unknown variant rule `{}`", other))),
+                    };
+
+                    let regions: Vec<String> = parts[2..].iter().map(|r| r.to_string()).collect();
+                    if rule == VariantRule::Autotile && regions.len() != AUTOTILE_VARIANTS {
+                        return Err(entry.error(format!("autotile needs {} regions, got {}",
+                                                       AUTOTILE_VARIANTS,
+                                                       regions.len())));
+                    }
+
+                    styles.push(TileStyleSource {
+                        kind: kind,
+                        rule: rule,
+                        regions: regions,
+                    });
+                }
+                "height" => {
+                    if parts.len() != 2 {
+                        return Err(entry.error("expected `<type> <pixels>`"));
+                    }
+
+                    let kind = parse_kind(parts[0], &entry)?;
+                    let height = match parts[1].parse::<f32>() {
+                        Ok(h) if h.is_finite() => h,
+                        _ => return Err(entry.error(format!("invalid height `{}`", parts[1]))),
+                    };
+                    heights.retain(|&(k, _)| k != kind);
+                    heights.push((kind, height));
+                }
+                other => return Err(entry.key_error(format!("unknown key `{}`", other))),
+            }
+        }
+
+        match atlas {
+            Some(atlas) => {
+                Ok(TileSetSource {
+                    atlas: atlas,
+                    styles: styles,
+                    heights: heights,
+                })
+            }
+            None => Err(ParseError::new(parse::last_line(source), "missing `atlas`")),
+        }
+    }
+}
+
+fn parse_kind(value: &str, entry: &Entry) -> Result<u8, ParseError> {
+    value.parse().map_err(|_| entry.error(format!("invalid tile type `{}`", value)))
+}
+
+#[derive(Debug, Clone)]
+struct TileStyle {
+    kind: u8,
+    rule: VariantRule,
+    variants: Vec<Handle<Sprite>>,
+}
+
+/// Sprites and heights of the tile types, resolved against an atlas.
+#[derive(Debug, Clone)]
+pub struct TileSet {
+    styles: Vec<TileStyle>,
+    heights: Vec<(u8, f32)>,
+}
+
+impl TileSet {
+    /// Tile set drawing every tile as an outline.
+    pub fn new() -> Self {
+        TileSet {
+            styles: Vec::new(),
+            heights: Vec::new(),
+        }
+    }
+
+    /// Load a tile set and the atlas it draws from.
+    pub fn load(ctx: &mut Context, assets: &mut Assets, path: &str) -> GameResult<TileSet> {
+        let mut file = ctx.filesystem.open(path)?;
+        let mut source = String::new();
+        file.read_to_string(&mut source)?;
+        let source = TileSetSource::parse(&source).map_err(|e| GameError::ResourceLoadError(format!("{}: {}", path, e)))?;
+
+        let atlas = assets.load_atlas(ctx, &source.atlas)?;
+        let mut styles = Vec::new();
+        for style in source.styles {
+            let mut variants = Vec::new();
+            for region in &style.regions {
+                match assets.atlas(atlas).region(region) {
+                    Some(sprite) => variants.push(sprite),
+                    None => {
+                        return Err(GameError::ResourceLoadError(format!("{}: region `{}` is not in {}",
+                                                                        path,
+                                                                        region,
+                                                                        source.atlas)))
+                    }
+                }
+            }
+
+            styles.push(TileStyle {
+                kind: style.kind,
+                rule: style.rule,
+                variants: variants,
+            });
+        }
+
+        Ok(TileSet {
+            styles: styles,
+            heights: source.heights,
+        })
+    }
+
+    /// How far a tile type is raised, in atlas pixels.
+    pub fn height(&self, kind: u8) -> f32 {
+        self.heights.iter().find(|&&(k, _)| k == kind).map_or(0.0, |&(_, h)| h)
+    }
+
+    /// Sprite a tile should be drawn with, none when its type has no style.
+    pub fn sprite_for(&self, board: &Board, coord: GridCoordinate) -> Option<Handle<Sprite>> {
+        let kind = board.get_tile(coord).get_tile_type();
+        let style = self.styles.iter().find(|s| s.kind == kind)?;
+
+        let index = match style.rule {
+            VariantRule::Random => {
+                let seed = (u64::from(coord[0]) << 32) | u64::from(coord[1]);
+                Rng::new(seed).range(style.variants.len() as u32) as usize
+            }
+            VariantRule::Autotile => neighbour_mask(board, coord, kind),
+        };
+        Some(style.variants[index])
+    }
+
+    /// Give every tile of a freshly loaded board its sprite.
+    pub fn assign(&self, board: &mut Board) {
+        for y in 0..board.height() {
+            for x in 0..board.width() {
+                let sprite = self.sprite_for(board, [x, y]);
+                board.set_sprite([x, y], sprite);
+            }
+        }
+    }
+
+    /// Give tiles that changed type their sprites again, together with their
+    /// neighbours since those autotile against them.
+    pub fn refresh(&self, board: &mut Board, changed: &[GridCoordinate]) {
+        let mut cells = Vec::with_capacity(changed.len() * 5);
+        for &coord in changed {
+            cells.push(coord);
+            cells.extend(board.adjacent(coord));
+        }
+        cells.sort();
+        cells.dedup();
+
+        for coord in cells {
+            let sprite = self.sprite_for(board, coord);
+            board.set_sprite(coord, sprite);
+        }
+    }
+}
+
+/// Which neighbours share a tile type, tiles off the board never do.
+fn neighbour_mask(board: &Board, coord: GridCoordinate, kind: u8) -> usize {
+    let sides = [(GridDirection::DirectionNorth, 1),
+                 (GridDirection::DirectionEast, 2),
+                 (GridDirection::DirectionSouth, 4),
+                 (GridDirection::DirectionWest, 8)];
+
+    sides.iter()
+        .filter(|&&(direction, _)| match board.get_neighbour(coord, direction) {
+            Some(next) => board.get_tile(next).get_tile_type() == kind,
+            None => false,
+        })
+        .map(|&(_, bit)| bit)
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cgmath::Vector2;
+    use specs::{DispatcherBuilder, World};
+
+    use components::graphics::{AssignTileSprites, GameBoard};
+    use components::territory::CapturedTiles;
+    use core::grid::Tile;
+
+    /// Autotiles type 1 with sprites numbered by their mask, type 0 has no
+    /// style.
+    fn autotiles() -> TileSet {
+        TileSet {
+            styles: vec![TileStyle {
+                             kind: 1,
+                             rule: VariantRule::Autotile,
+                             variants: (0..AUTOTILE_VARIANTS).map(Handle::new).collect(),
+                         }],
+            heights: Vec::new(),
+        }
+    }
+
+    fn mask(board: &Board, coord: GridCoordinate) -> usize {
+        neighbour_mask(board, coord, board.get_tile(coord).get_tile_type())
+    }
+
+    #[test]
+    fn edges_count_as_different_tiles() {
+        let board = Board::from_tiles(3, 3, 30.0, Vector2::new(0.0, 0.0), vec![Tile::with_type(1, true); 9]);
+
+        assert_eq!(mask(&board, [1, 1]), 15);
+        assert_eq!(mask(&board, [1, 0]), 2 + 4 + 8);
+        assert_eq!(mask(&board, [2, 1]), 1 + 4 + 8);
+        assert_eq!(mask(&board, [1, 2]), 1 + 2 + 8);
+        assert_eq!(mask(&board, [0, 1]), 1 + 2 + 4);
+        assert_eq!(mask(&board, [0, 0]), 2 + 4);
+        assert_eq!(mask(&board, [2, 0]), 4 + 8);
+        assert_eq!(mask(&board, [2, 2]), 1 + 8);
+        assert_eq!(mask(&board, [0, 2]), 1 + 2);
+
+        let single = Board::from_tiles(1, 1, 30.0, Vector2::new(0.0, 0.0), vec![Tile::with_type(1, true)]);
+        assert_eq!(mask(&single, [0, 0]), 0);
+    }
+
+    #[test]
+    fn masks_only_match_the_same_type() {
+        // Safe border around a single open tile
+        let board = Board::new(3, 3, 30.0, Vector2::new(0.0, 0.0));
+
+        assert_eq!(mask(&board, [1, 0]), 2 + 8);
+        assert_eq!(mask(&board, [0, 1]), 1 + 4);
+        assert_eq!(mask(&board, [1, 1]), 0);
+
+        let tile_set = autotiles();
+        assert_eq!(tile_set.sprite_for(&board, [1, 0]), Some(Handle::new(10)));
+        assert_eq!(tile_set.sprite_for(&board, [1, 1]), None);
+    }
+
+    #[test]
+    fn captures_refresh_sprites() {
+        let tile_set = autotiles();
+        let mut board = Board::new(5, 4, 30.0, Vector2::new(0.0, 0.0));
+        tile_set.assign(&mut board);
+
+        let captured = board.capture(&[[1, 1], [2, 1], [3, 1]], &[[2, 2]]);
+        assert_eq!(captured.len(), 3);
+        // Captured tiles lost their sprite and the border still shows the old
+        // masks
+        assert_eq!(board.get_tile([2, 1]).sprite(), None);
+        assert_eq!(board.get_tile([2, 0]).sprite(), Some(Handle::new(2 + 8)));
+
+        let mut world = World::new();
+        world.add_resource(tile_set.clone());
+        world.add_resource(CapturedTiles(captured));
+        world.add_resource(GameBoard(board));
+        let mut dispatcher = DispatcherBuilder::new().add(AssignTileSprites, "AssignTileSprites", &[]).build();
+        dispatcher.dispatch(&mut world.res);
+
+        let board = world.read_resource::<GameBoard>();
+        let board = &board.0;
+        for y in 0..board.height() {
+            for x in 0..board.width() {
+                assert_eq!(board.get_tile([x, y]).sprite(), tile_set.sprite_for(board, [x, y]), "[{}, {}]", x, y);
+            }
+        }
+        assert_eq!(board.get_tile([2, 1]).sprite(), Some(Handle::new(1 + 2 + 8)));
+        assert_eq!(board.get_tile([2, 0]).sprite(), Some(Handle::new(2 + 4 + 8)));
+        assert_eq!(board.get_tile([2, 2]).sprite(), None);
+    }
+}
@@ -0,0 +1,240 @@
+
+use std::f32::consts::PI;
+
+use cgmath::Vector2;
+use ggez::graphics::{Color, Point};
+use specs::{Component, VecStorage};
+
+/// Shape of a tween, maps linear progress in [0, 1] onto eased progress.
+///
+/// Every curve starts at 0 and ends exactly at 1, `BackOut` overshoots in
+/// between.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Easing {
+    Linear,
+    QuadIn,
+    QuadOut,
+    QuadInOut,
+    CubicIn,
+    CubicOut,
+    CubicInOut,
+    SineIn,
+    SineOut,
+    SineInOut,
+    BackOut,
+}
+
+impl Easing {
+    /// Eased progress, `t` is clamped to [0, 1] first.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.max(0.0).min(1.0);
+
+        match self {
+            Easing::Linear => t,
+            Easing::QuadIn => t * t,
+            Easing::QuadOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::QuadInOut => {
+                if t < 0.5 {
+                    2.0 * t * t
+                } else {
+                    1.0 - 2.0 * (1.0 - t) * (1.0 - t)
+                }
+            }
+            Easing::CubicIn => t * t * t,
+            Easing::CubicOut => 1.0 - (1.0 - t).powi(3),
+            Easing::CubicInOut => {
+                if t < 0.5 {
+                    4.0 * t * t * t
+                } else {
+                    1.0 - 4.0 * (1.0 - t).powi(3)
+                }
+            }
+            Easing::SineIn => 1.0 - (t * PI / 2.0).cos(),
+            Easing::SineOut => (t * PI / 2.0).sin(),
+            Easing::SineInOut => (1.0 - (t * PI).cos()) / 2.0,
+            Easing::BackOut => {
+                let c1 = 1.70158;
+                let u = t - 1.0;
+                1.0 + (c1 + 1.0) * u * u * u + c1 * u * u
+            }
+        }
+    }
+}
+
+/// Values that can be blended, `t` of 0 gives `self` and 1 gives `other`.
+pub trait Lerp {
+    fn lerp(&self, other: &Self, t: f32) -> Self;
+}
+
+impl Lerp for f32 {
+    fn lerp(&self, other: &f32, t: f32) -> f32 {
+        self + (other - self) * t
+    }
+}
+
+impl Lerp for Vector2<f32> {
+    fn lerp(&self, other: &Vector2<f32>, t: f32) -> Vector2<f32> {
+        Vector2::new(self.x.lerp(&other.x, t), self.y.lerp(&other.y, t))
+    }
+}
+
+impl Lerp for Point {
+    fn lerp(&self, other: &Point, t: f32) -> Point {
+        Point::new(self.x.lerp(&other.x, t), self.y.lerp(&other.y, t))
+    }
+}
+
+impl Lerp for Color {
+    fn lerp(&self, other: &Color, t: f32) -> Color {
+        Color::new(self.r.lerp(&other.r, t),
+                   self.g.lerp(&other.g, t),
+                   self.b.lerp(&other.b, t),
+                   self.a.lerp(&other.a, t))
+    }
+}
+
+/// Blends from one value to another over a number of seconds.
+///
+/// Works for anything that implements `Lerp`, positions, scales, colors or UI
+/// offsets, along an `Easing` curve. Once its duration has passed it holds
+/// the end value exactly.
+#[derive(Debug, Clone)]
+pub struct Tween<T> {
+    pub from: T,
+    pub to: T,
+    pub duration: f32,
+    pub easing: Easing,
+    elapsed: f32,
+}
+
+impl<T: Send + Sync + 'static> Component for Tween<T> {
+    type Storage = VecStorage<Self>;
+}
+
+impl<T: Lerp + Clone> Tween<T> {
+    pub fn new(from: T, to: T, duration: f32, easing: Easing) -> Self {
+        Tween {
+            from: from,
+            to: to,
+            duration: duration,
+            easing: easing,
+            elapsed: 0.0,
+        }
+    }
+
+    /// Move the tween forward, never past its end, and return the new value.
+    pub fn advance(&mut self, delta: f32) -> T {
+        self.elapsed = (self.elapsed + delta).min(self.duration.max(0.0));
+        self.value()
+    }
+
+    /// Linear progress in [0, 1].
+    pub fn progress(&self) -> f32 {
+        if self.duration <= 0.0 {
+            1.0
+        } else {
+            (self.elapsed / self.duration).min(1.0)
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.progress() >= 1.0
+    }
+
+    /// Current value, exactly `to` once finished.
+    pub fn value(&self) -> T {
+        if self.is_finished() {
+            return self.to.clone();
+        }
+        self.from.lerp(&self.to, self.easing.apply(self.progress()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ggez::event::Keycode;
+
+    use components::positioning::{Animating, GridPosition, Position};
+    use components::graphics::GameBoard;
+    use core::level::Level;
+    use simulation::Simulation;
+
+    const ALL_EASINGS: [Easing; 11] = [Easing::Linear,
+                                       Easing::QuadIn,
+                                       Easing::QuadOut,
+                                       Easing::QuadInOut,
+                                       Easing::CubicIn,
+                                       Easing::CubicOut,
+                                       Easing::CubicInOut,
+                                       Easing::SineIn,
+                                       Easing::SineOut,
+                                       Easing::SineInOut,
+                                       Easing::BackOut];
+
+    fn run_to_end(easing: Easing, deltas: &[f32]) -> Vector2<f32> {
+        let mut tween = Tween::new(Vector2::new(3.3, -7.1), Vector2::new(45.7, 12.9), 0.25, easing);
+        let mut value = tween.value();
+        for &delta in deltas.iter().cycle() {
+            if tween.is_finished() {
+                break;
+            }
+            value = tween.advance(delta);
+        }
+        value
+    }
+
+    #[test]
+    fn every_easing_ends_exactly_on_target() {
+        let target = Vector2::new(45.7, 12.9);
+        for &easing in &ALL_EASINGS {
+            assert_eq!(run_to_end(easing, &[1.0 / 60.0]), target, "{:?}", easing);
+            assert_eq!(run_to_end(easing, &[0.013, 0.041, 0.007, 0.029]), target, "{:?}", easing);
+            assert_eq!(run_to_end(easing, &[10.0]), target, "{:?}", easing);
+        }
+    }
+
+    #[test]
+    fn overshooting_delta_holds_target() {
+        for &easing in &ALL_EASINGS {
+            let mut tween = Tween::new(0.0, 1.0, 0.2, easing);
+            assert_eq!(tween.advance(0.15 + 0.15), 1.0);
+            assert_eq!(tween.advance(0.5), 1.0);
+            assert!(tween.is_finished());
+        }
+    }
+
+    #[test]
+    fn step_lands_on_tile_center() {
+        let level = Level::parse("player = 1 1\n\n[tiles]\n#####\n#...#\n#####\n").unwrap();
+        let mut simulation = Simulation::new();
+        let player = simulation.load_level(level).player;
+
+        let is_animating = |simulation: &Simulation| simulation.world.read::<Animating>().get(player).unwrap().0;
+
+        simulation.set_key(Keycode::D, true);
+        for _ in 0..10 {
+            simulation.step(0.013);
+            if is_animating(&simulation) {
+                break;
+            }
+        }
+        simulation.set_key(Keycode::D, false);
+        assert!(is_animating(&simulation));
+
+        let deltas = [0.011, 0.037, 0.02];
+        for &delta in deltas.iter().cycle().take(100) {
+            if !is_animating(&simulation) {
+                break;
+            }
+            simulation.step(delta);
+        }
+
+        let gp = simulation.world.read::<GridPosition>().get(player).unwrap().0;
+        let position = simulation.world.read::<Position>().get(player).unwrap().0;
+        let board = simulation.world.read_resource::<GameBoard>();
+        assert_eq!(gp, [2, 1]);
+        assert_eq!(position, board.0.get_tile_center_world_coordinate(gp));
+    }
+}
@@ -0,0 +1,13 @@
+#![cfg_attr(feature="clippy", feature(plugin))]
+#![cfg_attr(feature="clippy", plugin(clippy))]
+
+extern crate cgmath;
+extern crate ggez;
+extern crate specs;
+#[macro_use] extern crate specs_derive;
+
+pub mod core;
+pub mod components;
+pub mod resources;
+pub mod scenes;
+pub mod simulation;
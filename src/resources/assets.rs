@@ -0,0 +1,234 @@
+
+use std::collections::HashMap;
+use std::io::Read;
+
+use ggez::{Context, GameError, GameResult, graphics};
+use ggez::graphics::{DrawParam, Font, Image, Point, Rect};
+
+use core::animation::{Clip, ClipSet, ClipSetSource, Frame};
+use core::atlas::{AtlasSource, Region};
+use core::handle::Handle;
+
+/// Part of an image drawn as one picture.
+#[derive(Debug, Clone, Copy)]
+pub struct Sprite {
+    pub image: Handle<Image>,
+    /// Area of the image in texture coordinates, from 0 to 1.
+    pub src: Rect,
+    /// Size in pixels.
+    pub width: f32,
+    pub height: f32,
+}
+
+/// Named sprites sharing one image.
+#[derive(Debug)]
+pub struct Atlas {
+    pub image: Handle<Image>,
+    regions: HashMap<String, Handle<Sprite>>,
+}
+
+impl Atlas {
+    pub fn region(&self, name: &str) -> Option<Handle<Sprite>> {
+        self.regions.get(name).cloned()
+    }
+}
+
+/// Owns every image, font, atlas and clip set, each file is only loaded once.
+///
+/// Everything else holds handles, which are cheap to copy and store in
+/// components.
+pub struct Assets {
+    images: Vec<Image>,
+    fonts: Vec<Font>,
+    sprites: Vec<Sprite>,
+    atlases: Vec<Atlas>,
+    clip_sets: Vec<ClipSet>,
+    image_paths: HashMap<String, Handle<Image>>,
+    font_paths: HashMap<(String, u32), Handle<Font>>,
+    atlas_paths: HashMap<String, Handle<Atlas>>,
+    clip_paths: HashMap<String, Handle<ClipSet>>,
+    // Sprites covering a whole image
+    image_sprites: HashMap<Handle<Image>, Handle<Sprite>>,
+}
+
+impl Assets {
+    pub fn new() -> Self {
+        Assets {
+            images: Vec::new(),
+            fonts: Vec::new(),
+            sprites: Vec::new(),
+            atlases: Vec::new(),
+            clip_sets: Vec::new(),
+            image_paths: HashMap::new(),
+            font_paths: HashMap::new(),
+            atlas_paths: HashMap::new(),
+            clip_paths: HashMap::new(),
+            image_sprites: HashMap::new(),
+        }
+    }
+
+    pub fn load_image(&mut self, ctx: &mut Context, path: &str) -> GameResult<Handle<Image>> {
+        if let Some(&handle) = self.image_paths.get(path) {
+            return Ok(handle);
+        }
+
+        let handle = Handle::new(self.images.len());
+        self.images.push(Image::new(ctx, path)?);
+        self.image_paths.insert(path.to_string(), handle);
+        Ok(handle)
+    }
+
+    pub fn load_font(&mut self, ctx: &mut Context, path: &str, size: u32) -> GameResult<Handle<Font>> {
+        let key = (path.to_string(), size);
+        if let Some(&handle) = self.font_paths.get(&key) {
+            return Ok(handle);
+        }
+
+        let handle = Handle::new(self.fonts.len());
+        self.fonts.push(Font::new(ctx, path, size)?);
+        self.font_paths.insert(key, handle);
+        Ok(handle)
+    }
+
+    /// Sprite showing a whole image.
+    pub fn load_sprite(&mut self, ctx: &mut Context, path: &str) -> GameResult<Handle<Sprite>> {
+        let image = self.load_image(ctx, path)?;
+        if let Some(&handle) = self.image_sprites.get(&image) {
+            return Ok(handle);
+        }
+
+        let (width, height) = (self.images[image.index()].width(), self.images[image.index()].height());
+        let handle = self.add_sprite(image, [0, 0, width, height]);
+        self.image_sprites.insert(image, handle);
+        Ok(handle)
+    }
+
+    /// Load an atlas from its sidecar file, along with its image.
+    pub fn load_atlas(&mut self, ctx: &mut Context, path: &str) -> GameResult<Handle<Atlas>> {
+        if let Some(&handle) = self.atlas_paths.get(path) {
+            return Ok(handle);
+        }
+
+        let mut file = ctx.filesystem.open(path)?;
+        let mut source = String::new();
+        file.read_to_string(&mut source)?;
+        let source = AtlasSource::parse(&source).map_err(|e| GameError::ResourceLoadError(format!("{}: {}", path, e)))?;
+
+        let image = self.load_image(ctx, &source.image)?;
+        let (width, height) = (self.images[image.index()].width(), self.images[image.index()].height());
+
+        let mut regions = HashMap::new();
+        for (name, region) in source.regions {
+            if region[0] + region[2] > width || region[1] + region[3] > height {
+                return Err(GameError::ResourceLoadError(format!("{}: region `{}` is outside the image", path, name)));
+            }
+            regions.insert(name, self.add_sprite(image, region));
+        }
+
+        let handle = Handle::new(self.atlases.len());
+        self.atlases.push(Atlas {
+            image: image,
+            regions: regions,
+        });
+        self.atlas_paths.insert(path.to_string(), handle);
+        Ok(handle)
+    }
+
+    /// Load animation clips along with the atlas their frames are on.
+    pub fn load_clips(&mut self, ctx: &mut Context, path: &str) -> GameResult<Handle<ClipSet>> {
+        if let Some(&handle) = self.clip_paths.get(path) {
+            return Ok(handle);
+        }
+
+        let mut file = ctx.filesystem.open(path)?;
+        let mut source = String::new();
+        file.read_to_string(&mut source)?;
+        let source = ClipSetSource::parse(&source).map_err(|e| GameError::ResourceLoadError(format!("{}: {}", path, e)))?;
+
+        let atlas = self.load_atlas(ctx, &source.atlas)?;
+        let mut clips = Vec::new();
+        for clip in source.clips {
+            let mut frames = Vec::new();
+            for (region, duration) in clip.frames {
+                match self.atlas(atlas).region(&region) {
+                    Some(sprite) => {
+                        frames.push(Frame {
+                            sprite: sprite,
+                            duration: duration,
+                        })
+                    }
+                    None => {
+                        return Err(GameError::ResourceLoadError(format!("{}: region `{}` is not in {}",
+                                                                        path,
+                                                                        region,
+                                                                        source.atlas)))
+                    }
+                }
+            }
+
+            let key = (clip.motion, clip.direction);
+            clips.push((key,
+                        Clip {
+                            mode: clip.mode,
+                            frames: frames,
+                        }));
+        }
+
+        let handle = Handle::new(self.clip_sets.len());
+        self.clip_sets.push(ClipSet::new(clips));
+        self.clip_paths.insert(path.to_string(), handle);
+        Ok(handle)
+    }
+
+    fn add_sprite(&mut self, image: Handle<Image>, region: Region) -> Handle<Sprite> {
+        let (width, height) = {
+            let image = &self.images[image.index()];
+            (image.width() as f32, image.height() as f32)
+        };
+
+        let handle = Handle::new(self.sprites.len());
+        self.sprites.push(Sprite {
+            image: image,
+            src: Rect::new(region[0] as f32 / width,
+                           region[1] as f32 / height,
+                           region[2] as f32 / width,
+                           region[3] as f32 / height),
+            width: region[2] as f32,
+            height: region[3] as f32,
+        });
+        handle
+    }
+
+    pub fn image(&self, handle: Handle<Image>) -> &Image {
+        &self.images[handle.index()]
+    }
+
+    pub fn font(&self, handle: Handle<Font>) -> &Font {
+        &self.fonts[handle.index()]
+    }
+
+    pub fn sprite(&self, handle: Handle<Sprite>) -> &Sprite {
+        &self.sprites[handle.index()]
+    }
+
+    pub fn atlas(&self, handle: Handle<Atlas>) -> &Atlas {
+        &self.atlases[handle.index()]
+    }
+
+    pub fn clips(&self, handle: Handle<ClipSet>) -> &ClipSet {
+        &self.clip_sets[handle.index()]
+    }
+
+    /// Draw a sprite centered on a screen point.
+    pub fn draw_sprite(&self, ctx: &mut Context, handle: Handle<Sprite>, dest: Point, scale: f32) -> GameResult<()> {
+        let sprite = self.sprite(handle);
+        graphics::draw_ex(ctx,
+                          &self.images[sprite.image.index()],
+                          DrawParam {
+                              src: sprite.src,
+                              dest: dest,
+                              scale: Point::new(scale, scale),
+                              ..Default::default()
+                          })
+    }
+}
@@ -0,0 +1,282 @@
+
+use specs::{System, HashMapStorage, Fetch, FetchMut, ReadStorage, World, DispatcherBuilder};
+use ggez::graphics::Point;
+use cgmath::Vector2;
+
+use resources::delta_time;
+use components::graphics::GameBoard;
+use components::positioning::Position;
+use core::iso_coords::IsoCoord;
+
+const MIN_ZOOM: f32 = 0.25;
+const MAX_ZOOM: f32 = 4.0;
+
+/// Marks the entity the camera should follow.
+#[derive(Component, Debug)]
+#[component(HashMapStorage)]
+pub struct CameraTarget;
+
+/// Maps world coordinates to the screen.
+///
+/// The camera lives in isometric space, world points are first projected with
+/// `IsoCoord::from_cartesian` and then offset, scaled and centered on screen.
+#[derive(Debug)]
+pub struct Camera {
+    // Isometric point shown in the middle of the screen
+    position: Vector2<f32>,
+    zoom: f32,
+    viewport: Vector2<f32>,
+    /// Half extents, in screen pixels, the target may move without the camera
+    /// following.
+    pub dead_zone: Vector2<f32>,
+    /// How fast the camera catches up with its target, higher is snappier.
+    pub smoothing: f32,
+    // Isometric area the camera has to stay within
+    bounds: Option<(Vector2<f32>, Vector2<f32>)>,
+}
+
+impl Camera {
+    pub fn new(viewport_width: f32, viewport_height: f32) -> Self {
+        Camera {
+            position: Vector2::new(0.0, 0.0),
+            zoom: 1.0,
+            viewport: Vector2::new(viewport_width, viewport_height),
+            dead_zone: Vector2::new(60.0, 40.0),
+            smoothing: 6.0,
+            bounds: None,
+        }
+    }
+
+    /// Project a world point onto the screen.
+    pub fn world_to_screen(&self, point: Vector2<f32>) -> Point {
+        self.iso_to_screen(IsoCoord::from_cartesian(point.x, point.y))
+    }
+
+    /// Move an isometric point onto the screen.
+    pub fn iso_to_screen(&self, iso: IsoCoord) -> Point {
+        Point::new((iso.x - self.position.x) * self.zoom + self.viewport.x / 2.0,
+                   (iso.y - self.position.y) * self.zoom + self.viewport.y / 2.0)
+    }
+
+    /// Isometric point under a screen position.
+    pub fn screen_to_iso(&self, point: Point) -> IsoCoord {
+        IsoCoord::new((point.x - self.viewport.x / 2.0) / self.zoom + self.position.x,
+                      (point.y - self.viewport.y / 2.0) / self.zoom + self.position.y)
+    }
+
+    /// World point under a screen position, inverse of `world_to_screen`.
+    pub fn screen_to_world(&self, point: Point) -> Vector2<f32> {
+        let cartesian = self.screen_to_iso(point).to_cartesian();
+        Vector2::new(cartesian.x, cartesian.y)
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom.max(MIN_ZOOM).min(MAX_ZOOM);
+        self.clamp();
+    }
+
+    pub fn set_viewport(&mut self, width: f32, height: f32) {
+        self.viewport = Vector2::new(width, height);
+        self.clamp();
+    }
+
+    /// Keep the camera within an isometric area, usually the board.
+    pub fn set_bounds(&mut self, min: Vector2<f32>, max: Vector2<f32>) {
+        self.bounds = Some((min, max));
+        self.clamp();
+    }
+
+    /// Center the camera on a world point right away.
+    pub fn look_at(&mut self, point: Vector2<f32>) {
+        let iso = IsoCoord::from_cartesian(point.x, point.y);
+        self.position = Vector2::new(iso.x, iso.y);
+        self.clamp();
+    }
+
+    /// Move the camera by an offset given in screen pixels.
+    pub fn pan(&mut self, offset: Vector2<f32>) {
+        self.position = self.position + offset / self.zoom;
+        self.clamp();
+    }
+
+    /// Ease towards a world point once it leaves the dead zone.
+    pub fn follow(&mut self, point: Vector2<f32>, delta: f32) {
+        let iso = IsoCoord::from_cartesian(point.x, point.y);
+        let offset = Vector2::new(iso.x, iso.y) - self.position;
+        let dead_zone = self.dead_zone / self.zoom;
+
+        let excess = Vector2::new(outside(offset.x, dead_zone.x), outside(offset.y, dead_zone.y));
+
+        // Frame rate independent exponential smoothing
+        let t = 1.0 - (-self.smoothing * delta).exp();
+        self.position = self.position + excess * t;
+        self.clamp();
+    }
+
+    fn clamp(&mut self) {
+        let (min, max) = match self.bounds {
+            Some(b) => b,
+            None => return,
+        };
+
+        let half_view = self.viewport / (2.0 * self.zoom);
+        self.position.x = clamp_axis(self.position.x, min.x, max.x, half_view.x);
+        self.position.y = clamp_axis(self.position.y, min.y, max.y, half_view.y);
+    }
+}
+
+/// Part of an offset that lies outside a symmetric dead zone.
+fn outside(offset: f32, dead_zone: f32) -> f32 {
+    if offset > dead_zone {
+        offset - dead_zone
+    } else if offset < -dead_zone {
+        offset + dead_zone
+    } else {
+        0.0
+    }
+}
+
+/// Keep a view of half size `half_view` inside [min, max], centering it when
+/// the area is smaller than the view.
+fn clamp_axis(value: f32, min: f32, max: f32, half_view: f32) -> f32 {
+    if max - min <= 2.0 * half_view {
+        (min + max) / 2.0
+    } else {
+        value.max(min + half_view).min(max - half_view)
+    }
+}
+
+pub struct FollowCamera;
+
+impl<'a> System<'a> for FollowCamera {
+    type SystemData = (Fetch<'a, delta_time::DeltaTime>,
+     Fetch<'a, GameBoard>,
+     FetchMut<'a, Camera>,
+     ReadStorage<'a, CameraTarget>,
+     ReadStorage<'a, Position>);
+
+    fn run(&mut self, (delta, board, mut camera, target, pos): Self::SystemData) {
+        use specs::Join;
+
+        let (min, max) = board.0.iso_bounds();
+        camera.set_bounds(min, max);
+
+        for (_t, pos) in (&target, &pos).join() {
+            camera.follow(pos.0, delta.0);
+        }
+    }
+}
+
+pub fn init_world<'a, 'b>(world: &mut World,
+                          dispatcher_builder: DispatcherBuilder<'a, 'b>)
+                          -> DispatcherBuilder<'a, 'b> {
+    world.register::<CameraTarget>();
+
+    // Viewport is set by whoever owns the window
+    world.add_resource(Camera::new(0.0, 0.0));
+
+    dispatcher_builder.add(FollowCamera, "FollowCamera", &["Move"])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Vector2<f32>, b: Vector2<f32>) {
+        assert!((a.x - b.x).abs() < 1e-3 && (a.y - b.y).abs() < 1e-3, "{:?} != {:?}", a, b);
+    }
+
+    /// 200 by 100 viewport over an isometric area of 1000 by 1000.
+    fn bounded() -> Camera {
+        let mut camera = Camera::new(200.0, 100.0);
+        camera.set_bounds(Vector2::new(0.0, 0.0), Vector2::new(1000.0, 1000.0));
+        camera
+    }
+
+    #[test]
+    fn screen_round_trips_at_any_zoom() {
+        let points = [Vector2::new(0.0, 0.0), Vector2::new(45.0, 15.0), Vector2::new(-120.5, 300.25)];
+
+        for &zoom in &[0.25, 0.5, 1.0, 1.5, 4.0] {
+            let mut camera = Camera::new(800.0, 600.0);
+            camera.look_at(Vector2::new(70.0, 35.0));
+            camera.set_zoom(zoom);
+
+            let center = camera.world_to_screen(Vector2::new(70.0, 35.0));
+            assert_close(Vector2::new(center.x, center.y), Vector2::new(400.0, 300.0));
+
+            for &point in &points {
+                assert_close(camera.screen_to_world(camera.world_to_screen(point)), point);
+            }
+        }
+    }
+
+    #[test]
+    fn zoom_scales_around_the_center() {
+        let mut camera = Camera::new(800.0, 600.0);
+        let before = camera.world_to_screen(Vector2::new(30.0, 0.0));
+
+        camera.set_zoom(2.0);
+        let after = camera.world_to_screen(Vector2::new(30.0, 0.0));
+        assert_close(Vector2::new(after.x - 400.0, after.y - 300.0),
+                     Vector2::new(2.0 * (before.x - 400.0), 2.0 * (before.y - 300.0)));
+    }
+
+    #[test]
+    fn zoom_is_clamped() {
+        let mut camera = Camera::new(800.0, 600.0);
+
+        camera.set_zoom(0.01);
+        assert_eq!(camera.zoom(), MIN_ZOOM);
+        camera.set_zoom(100.0);
+        assert_eq!(camera.zoom(), MAX_ZOOM);
+        camera.set_zoom(1.5);
+        assert_eq!(camera.zoom(), 1.5);
+    }
+
+    #[test]
+    fn view_stays_within_bounds() {
+        let mut camera = bounded();
+
+        camera.pan(Vector2::new(-5000.0, -5000.0));
+        assert_close(camera.position, Vector2::new(100.0, 50.0));
+        camera.pan(Vector2::new(5000.0, 5000.0));
+        assert_close(camera.position, Vector2::new(900.0, 950.0));
+
+        // Zooming in shrinks the view, so it may get closer to the edges
+        camera.set_zoom(2.0);
+        camera.pan(Vector2::new(5000.0, 5000.0));
+        assert_close(camera.position, Vector2::new(950.0, 975.0));
+
+        // Zooming out again pushes it back in
+        camera.set_zoom(1.0);
+        assert_close(camera.position, Vector2::new(900.0, 950.0));
+    }
+
+    #[test]
+    fn small_areas_are_centered() {
+        let mut camera = bounded();
+        camera.set_bounds(Vector2::new(0.0, 0.0), Vector2::new(150.0, 1000.0));
+        camera.pan(Vector2::new(-5000.0, 0.0));
+        assert_close(camera.position, Vector2::new(75.0, 50.0));
+    }
+
+    #[test]
+    fn follow_ignores_the_dead_zone() {
+        let mut camera = Camera::new(800.0, 600.0);
+        camera.dead_zone = Vector2::new(60.0, 40.0);
+
+        // Straight down in the world is straight down on screen as well
+        let inside = camera.screen_to_world(Point::new(400.0, 330.0));
+        camera.follow(inside, 1.0);
+        assert_close(camera.position, Vector2::new(0.0, 0.0));
+
+        let outside = camera.screen_to_world(Point::new(400.0, 400.0));
+        camera.follow(outside, 100.0);
+        assert_close(camera.position, Vector2::new(0.0, 60.0));
+    }
+}
@@ -0,0 +1,163 @@
+
+use specs::{World, DispatcherBuilder};
+
+/// Simulation steps per second.
+pub const DEFAULT_TICK_RATE: f32 = 60.0;
+
+// Upper bound of steps per frame, so a long hitch does not snowball
+const MAX_TICKS_PER_FRAME: u32 = 8;
+
+/// Fixed time step accumulator.
+///
+/// Frame times are collected and paid out as whole ticks of a fixed length,
+/// whatever is left over is exposed as an interpolation factor for rendering.
+pub struct SimulationClock {
+    tick: f32,
+    accumulator: f32,
+    time_scale: f32,
+    paused: bool,
+}
+
+impl SimulationClock {
+    pub fn new(tick_rate: f32) -> Self {
+        SimulationClock {
+            tick: 1.0 / tick_rate,
+            accumulator: 0.0,
+            time_scale: 1.0,
+            paused: false,
+        }
+    }
+
+    /// Length of one tick in seconds.
+    pub fn tick(&self) -> f32 {
+        self.tick
+    }
+
+    /// Add the time of a frame and return how many ticks should be run.
+    pub fn advance(&mut self, frame_delta: f32) -> u32 {
+        if self.paused {
+            return 0;
+        }
+
+        self.accumulator += frame_delta * self.time_scale;
+
+        let mut ticks = 0;
+        while self.accumulator >= self.tick && ticks < MAX_TICKS_PER_FRAME {
+            self.accumulator -= self.tick;
+            ticks += 1;
+        }
+
+        // Drop the ticks we could not catch up with, keep the part of the
+        // next one so alpha stays below 1
+        if self.accumulator >= self.tick {
+            self.accumulator %= self.tick;
+        }
+        ticks
+    }
+
+    /// How far, between 0 and 1, we are into the next tick.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.tick).min(1.0)
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+    }
+
+    pub fn time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    /// Speed the simulation up or slow it down, 1 is real time.
+    pub fn set_time_scale(&mut self, time_scale: f32) {
+        self.time_scale = time_scale.max(0.0);
+    }
+}
+
+pub fn init_world<'a, 'b>(world: &mut World,
+                          dispatcher_builder: DispatcherBuilder<'a, 'b>)
+                          -> DispatcherBuilder<'a, 'b> {
+    world.add_resource(SimulationClock::new(DEFAULT_TICK_RATE));
+    dispatcher_builder
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A power of two keeps the tick length exact
+    const RATE: f32 = 64.0;
+    const TICK: f32 = 1.0 / RATE;
+
+    #[test]
+    fn fixed_ticks_per_advance() {
+        let mut clock = SimulationClock::new(RATE);
+        assert_eq!(clock.tick(), TICK);
+
+        assert_eq!(clock.advance(2.0 * TICK), 2);
+        assert_eq!(clock.advance(TICK / 2.0), 0);
+        assert_eq!(clock.alpha(), 0.5);
+        assert_eq!(clock.advance(TICK / 2.0), 1);
+        assert_eq!(clock.alpha(), 0.0);
+        assert_eq!(clock.advance(TICK * 1.75), 1);
+        assert_eq!(clock.alpha(), 0.75);
+    }
+
+    #[test]
+    fn alpha_stays_below_one() {
+        let mut clock = SimulationClock::new(DEFAULT_TICK_RATE);
+        for &frame in &[0.003, 0.017, 1.0 / 60.0, 0.0333, 0.1, 0.5, 1.0, 0.0, 0.0167] {
+            clock.advance(frame);
+            let alpha = clock.alpha();
+            assert!(alpha >= 0.0 && alpha < 1.0, "alpha {} after a frame of {}", alpha, frame);
+        }
+    }
+
+    #[test]
+    fn long_frames_are_clamped() {
+        let mut clock = SimulationClock::new(RATE);
+
+        assert_eq!(clock.advance(1.0), MAX_TICKS_PER_FRAME);
+        assert!(clock.alpha() < 1.0);
+        // The backlog is gone, not paid out over the next frames
+        assert_eq!(clock.advance(0.0), 0);
+
+        assert_eq!(clock.advance(TICK * (MAX_TICKS_PER_FRAME as f32 + 2.25)), MAX_TICKS_PER_FRAME);
+        assert_eq!(clock.alpha(), 0.25);
+    }
+
+    #[test]
+    fn pause_holds_time() {
+        let mut clock = SimulationClock::new(RATE);
+        clock.advance(TICK / 2.0);
+
+        clock.set_paused(true);
+        assert!(clock.is_paused());
+        assert_eq!(clock.advance(1.0), 0);
+        assert_eq!(clock.alpha(), 0.5);
+
+        clock.set_paused(false);
+        assert_eq!(clock.advance(TICK / 2.0), 1);
+    }
+
+    #[test]
+    fn time_scale_stretches_frames() {
+        let mut clock = SimulationClock::new(RATE);
+
+        clock.set_time_scale(0.5);
+        assert_eq!(clock.time_scale(), 0.5);
+        assert_eq!(clock.advance(2.0 * TICK), 1);
+
+        clock.set_time_scale(2.0);
+        assert_eq!(clock.advance(TICK), 2);
+
+        clock.set_time_scale(-1.0);
+        assert_eq!(clock.time_scale(), 0.0);
+        assert_eq!(clock.advance(1.0), 0);
+        assert_eq!(clock.alpha(), 0.0);
+    }
+}
@@ -0,0 +1,186 @@
+
+use std::mem;
+
+use specs::{System, Fetch, FetchMut, World, DispatcherBuilder};
+
+use components::graphics::GameBoard;
+use core::grid::Board;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum LevelEvent {
+    /// The level with the given index reached its target.
+    Complete(usize),
+    /// The last level in the list was completed.
+    AllComplete,
+}
+
+/// Tracks how much of the board is captured and which level is played.
+pub struct LevelProgress {
+    levels: Vec<String>,
+    current: usize,
+    target: f32,
+    captured: f32,
+    complete: bool,
+    events: Vec<LevelEvent>,
+}
+
+impl LevelProgress {
+    /// Create a tracker for an ordered list of level paths.
+    pub fn new(levels: Vec<String>) -> Self {
+        LevelProgress {
+            levels: levels,
+            current: 0,
+            target: 100.0,
+            captured: 0.0,
+            complete: false,
+            events: Vec::new(),
+        }
+    }
+
+    /// Path of the level that should be played.
+    pub fn current_level(&self) -> Option<&str> {
+        self.levels.get(self.current).map(|l| l.as_str())
+    }
+
+    /// Continue the progression from a level, it is added as the only level
+    /// when it is not part of the list.
+    pub fn start_at(&mut self, path: &str) {
+        match self.levels.iter().position(|l| l == path) {
+            Some(i) => self.current = i,
+            None => {
+                self.levels = vec![path.to_string()];
+                self.current = 0;
+            }
+        }
+    }
+
+    /// Reset the tracker for a freshly loaded level.
+    pub fn start_level(&mut self, target: f32) {
+        self.target = target;
+        self.captured = 0.0;
+        self.complete = false;
+    }
+
+    /// Recompute the captured percentage and raise events once the target is
+    /// reached.
+    pub fn update(&mut self, board: &Board) {
+        self.captured = board.fill_percentage();
+
+        if self.complete || self.captured < self.target {
+            return;
+        }
+
+        self.complete = true;
+        self.events.push(LevelEvent::Complete(self.current));
+
+        if self.current + 1 < self.levels.len() {
+            self.current += 1;
+        } else {
+            self.events.push(LevelEvent::AllComplete);
+        }
+    }
+
+    pub fn captured(&self) -> f32 {
+        self.captured
+    }
+
+    pub fn target(&self) -> f32 {
+        self.target
+    }
+
+    /// Take all events raised since the last call.
+    pub fn drain_events(&mut self) -> Vec<LevelEvent> {
+        mem::replace(&mut self.events, Vec::new())
+    }
+}
+
+pub struct CheckProgress;
+
+impl<'a> System<'a> for CheckProgress {
+    type SystemData = (Fetch<'a, GameBoard>, FetchMut<'a, LevelProgress>);
+
+    fn run(&mut self, (board, mut progress): Self::SystemData) {
+        progress.update(&board.0);
+    }
+}
+
+pub fn init_world<'a, 'b>(world: &mut World,
+                          dispatcher_builder: DispatcherBuilder<'a, 'b>)
+                          -> DispatcherBuilder<'a, 'b> {
+    world.add_resource(LevelProgress::new(Vec::new()));
+
+    dispatcher_builder.add(CheckProgress, "CheckProgress", &["Capture"])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use cgmath::Vector2;
+
+    use core::grid::Tile;
+
+    /// Board of four tiles, `safe` of them captured.
+    fn board(safe: usize) -> Board {
+        let tiles = (0..4).map(|i| Tile::with_type(if i < safe { 1 } else { 0 }, i < safe)).collect();
+        Board::from_tiles(2, 2, 30.0, Vector2::new(0.0, 0.0), tiles)
+    }
+
+    fn progress(levels: &[&str]) -> LevelProgress {
+        let mut progress = LevelProgress::new(levels.iter().map(|l| l.to_string()).collect());
+        progress.start_level(75.0);
+        progress
+    }
+
+    #[test]
+    fn below_target_raises_nothing() {
+        let mut progress = progress(&["a", "b"]);
+        progress.update(&board(2));
+        assert_eq!(progress.captured(), 50.0);
+        assert!(progress.drain_events().is_empty());
+        assert_eq!(progress.current_level(), Some("a"));
+    }
+
+    #[test]
+    fn reaching_target_completes_once_and_advances() {
+        let mut progress = progress(&["a", "b"]);
+        progress.update(&board(3));
+        assert_eq!(progress.drain_events(), vec![LevelEvent::Complete(0)]);
+        assert_eq!(progress.current_level(), Some("b"));
+
+        // Staying above the target does not raise it again
+        progress.update(&board(3));
+        progress.update(&board(4));
+        assert!(progress.drain_events().is_empty());
+        assert_eq!(progress.current_level(), Some("b"));
+    }
+
+    #[test]
+    fn next_level_starts_fresh() {
+        let mut progress = progress(&["a", "b", "c"]);
+        progress.update(&board(4));
+        progress.drain_events();
+
+        progress.start_level(50.0);
+        progress.update(&board(1));
+        assert!(progress.drain_events().is_empty());
+        progress.update(&board(2));
+        assert_eq!(progress.drain_events(), vec![LevelEvent::Complete(1)]);
+        assert_eq!(progress.current_level(), Some("c"));
+    }
+
+    #[test]
+    fn last_level_completes_everything() {
+        let mut progress = progress(&["a", "b"]);
+        progress.update(&board(4));
+        progress.drain_events();
+        progress.start_level(75.0);
+
+        progress.update(&board(3));
+        assert_eq!(progress.drain_events(), vec![LevelEvent::Complete(1), LevelEvent::AllComplete]);
+        assert_eq!(progress.current_level(), Some("b"));
+
+        progress.update(&board(4));
+        assert!(progress.drain_events().is_empty());
+    }
+}
@@ -1,3 +1,9 @@
 
+pub mod assets;
+pub mod camera;
+pub mod clock;
 pub mod delta_time;
+pub mod level_progress;
+pub mod occupancy;
+pub mod scene_transitions;
 pub mod screen_params;
@@ -0,0 +1,248 @@
+
+use std::collections::HashMap;
+
+use specs::{System, Entity, Entities, FetchMut, ReadStorage, World, DispatcherBuilder};
+
+use components::positioning::GridPosition;
+use core::grid::GridCoordinate;
+
+/// Which entities stand on which tile, by their `GridPosition`.
+#[derive(Debug)]
+pub struct Occupancy {
+    tiles: HashMap<GridCoordinate, Vec<Entity>>,
+    entities: HashMap<Entity, GridCoordinate>,
+}
+
+impl Occupancy {
+    pub fn new() -> Self {
+        Occupancy {
+            tiles: HashMap::new(),
+            entities: HashMap::new(),
+        }
+    }
+
+    /// Put an entity on a tile, taking it off the tile it was on before.
+    pub fn insert(&mut self, entity: Entity, coord: GridCoordinate) {
+        if self.entities.get(&entity) == Some(&coord) {
+            return;
+        }
+        self.remove(entity);
+
+        self.entities.insert(entity, coord);
+        self.tiles.entry(coord).or_insert_with(Vec::new).push(entity);
+    }
+
+    /// Take an entity off the board, returns the tile it was on.
+    pub fn remove(&mut self, entity: Entity) -> Option<GridCoordinate> {
+        let coord = self.entities.remove(&entity)?;
+
+        let empty = match self.tiles.get_mut(&coord) {
+            Some(on_tile) => {
+                on_tile.retain(|&e| e != entity);
+                on_tile.is_empty()
+            }
+            None => false,
+        };
+        if empty {
+            self.tiles.remove(&coord);
+        }
+        Some(coord)
+    }
+
+    pub fn clear(&mut self) {
+        self.tiles.clear();
+        self.entities.clear();
+    }
+
+    /// Tile an entity stands on.
+    pub fn coord_of(&self, entity: Entity) -> Option<GridCoordinate> {
+        self.entities.get(&entity).cloned()
+    }
+
+    /// Entities on a tile.
+    pub fn at(&self, coord: GridCoordinate) -> &[Entity] {
+        match self.tiles.get(&coord) {
+            Some(on_tile) => on_tile.as_slice(),
+            None => &[],
+        }
+    }
+
+    /// First entity on a tile matching a predicate.
+    pub fn find_at<F>(&self, coord: GridCoordinate, predicate: F) -> Option<Entity>
+        where F: Fn(Entity) -> bool
+    {
+        self.at(coord).iter().cloned().find(|&e| predicate(e))
+    }
+
+    /// Entities within a rectangle of tiles, both corners included.
+    pub fn in_rect(&self, min: GridCoordinate, max: GridCoordinate) -> Vec<(GridCoordinate, Entity)> {
+        if max[0] < min[0] || max[1] < min[1] {
+            return Vec::new();
+        }
+
+        // Scan whichever is smaller, the rectangle or the occupied tiles
+        let area = (u64::from(max[0] - min[0]) + 1) * (u64::from(max[1] - min[1]) + 1);
+        if area <= self.tiles.len() as u64 {
+            self.scan_rect(min, max)
+        } else {
+            self.scan_tiles(min, max)
+        }
+    }
+
+    fn scan_rect(&self, min: GridCoordinate, max: GridCoordinate) -> Vec<(GridCoordinate, Entity)> {
+        let mut found = Vec::new();
+        let (x0, x1) = (u64::from(min[0]), u64::from(max[0]));
+        let (y0, y1) = (u64::from(min[1]), u64::from(max[1]));
+        for y in y0..y1 + 1 {
+            for x in x0..x1 + 1 {
+                let coord = [x as u32, y as u32];
+                for &entity in self.at(coord) {
+                    found.push((coord, entity));
+                }
+            }
+        }
+        found
+    }
+
+    fn scan_tiles(&self, min: GridCoordinate, max: GridCoordinate) -> Vec<(GridCoordinate, Entity)> {
+        let mut found = Vec::new();
+        for (&coord, on_tile) in &self.tiles {
+            if coord[0] >= min[0] && coord[0] <= max[0] && coord[1] >= min[1] && coord[1] <= max[1] {
+                found.extend(on_tile.iter().map(|&e| (coord, e)));
+            }
+        }
+        found
+    }
+
+    /// Entities at most `radius` tiles away from a tile in either axis.
+    pub fn around(&self, coord: GridCoordinate, radius: u32) -> Vec<(GridCoordinate, Entity)> {
+        let min = [coord[0].saturating_sub(radius), coord[1].saturating_sub(radius)];
+        let max = [coord[0].saturating_add(radius), coord[1].saturating_add(radius)];
+        self.in_rect(min, max)
+    }
+}
+
+/// Keeps the occupancy index in line with `GridPosition`.
+pub struct IndexOccupancy;
+
+impl<'a> System<'a> for IndexOccupancy {
+    type SystemData = (Entities<'a>, FetchMut<'a, Occupancy>, ReadStorage<'a, GridPosition>);
+
+    fn run(&mut self, (entities, mut occupancy, grid_pos): Self::SystemData) {
+        use specs::Join;
+
+        // Drop entities that were deleted or lost their position
+        let stale: Vec<Entity> = occupancy.entities
+            .keys()
+            .cloned()
+            .filter(|&e| !entities.is_alive(e) || grid_pos.get(e).is_none())
+            .collect();
+        for entity in stale {
+            occupancy.remove(entity);
+        }
+
+        for (entity, gp) in (&*entities, &grid_pos).join() {
+            occupancy.insert(entity, gp.0);
+        }
+    }
+}
+
+pub fn init_world<'a, 'b>(world: &mut World,
+                          dispatcher_builder: DispatcherBuilder<'a, 'b>)
+                          -> DispatcherBuilder<'a, 'b> {
+    world.add_resource(Occupancy::new());
+
+    dispatcher_builder.add(IndexOccupancy, "IndexOccupancy", &["Move", "Bounce"])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use specs::Dispatcher;
+
+    fn indexed_world() -> (World, Dispatcher<'static, 'static>) {
+        let mut world = World::new();
+        world.register::<GridPosition>();
+        world.add_resource(Occupancy::new());
+        let dispatcher = DispatcherBuilder::new().add(IndexOccupancy, "IndexOccupancy", &[]).build();
+        (world, dispatcher)
+    }
+
+    fn sorted(mut found: Vec<(GridCoordinate, Entity)>) -> Vec<(GridCoordinate, Entity)> {
+        found.sort_by_key(|&(c, e)| (c, e.id()));
+        found
+    }
+
+    #[test]
+    fn index_follows_create_move_and_delete() {
+        let (mut world, mut dispatcher) = indexed_world();
+        let a = world.create_entity().with(GridPosition([1, 1])).build();
+        let b = world.create_entity().with(GridPosition([1, 1])).build();
+        let c = world.create_entity().with(GridPosition([4, 2])).build();
+        dispatcher.dispatch(&mut world.res);
+        {
+            let occupancy = world.read_resource::<Occupancy>();
+            assert_eq!(occupancy.at([1, 1]), &[a, b]);
+            assert_eq!(occupancy.at([4, 2]), &[c]);
+        }
+
+        world.write::<GridPosition>().insert(a, GridPosition([2, 1]));
+        dispatcher.dispatch(&mut world.res);
+        {
+            let occupancy = world.read_resource::<Occupancy>();
+            assert_eq!(occupancy.at([1, 1]), &[b]);
+            assert_eq!(occupancy.at([2, 1]), &[a]);
+            assert_eq!(occupancy.coord_of(a), Some([2, 1]));
+        }
+
+        world.entities().delete(b).unwrap();
+        world.write::<GridPosition>().remove(c);
+        world.maintain();
+        dispatcher.dispatch(&mut world.res);
+
+        let occupancy = world.read_resource::<Occupancy>();
+        assert!(occupancy.at([1, 1]).is_empty());
+        assert!(occupancy.at([4, 2]).is_empty());
+        assert_eq!(occupancy.coord_of(b), None);
+        assert_eq!(occupancy.coord_of(c), None);
+        assert_eq!(occupancy.tiles.len(), 1);
+    }
+
+    #[test]
+    fn rect_and_radius_queries() {
+        let (mut world, mut dispatcher) = indexed_world();
+        let near = world.create_entity().with(GridPosition([0, 0])).build();
+        let mid = world.create_entity().with(GridPosition([3, 3])).build();
+        let far = world.create_entity().with(GridPosition([9, 5])).build();
+        dispatcher.dispatch(&mut world.res);
+
+        let occupancy = world.read_resource::<Occupancy>();
+        assert_eq!(sorted(occupancy.in_rect([0, 0], [3, 3])), sorted(vec![([0, 0], near), ([3, 3], mid)]));
+        assert_eq!(occupancy.in_rect([3, 3], [3, 3]), vec![([3, 3], mid)]);
+        assert!(occupancy.in_rect([4, 0], [8, 9]).is_empty());
+        assert!(occupancy.in_rect([3, 3], [2, 2]).is_empty());
+
+        // Corners are included and the radius stops at the board origin
+        assert_eq!(sorted(occupancy.around([1, 1], 2)), sorted(vec![([0, 0], near), ([3, 3], mid)]));
+        assert_eq!(occupancy.around([8, 4], 1), vec![([9, 5], far)]);
+        assert_eq!(occupancy.around([0, 0], 0), vec![([0, 0], near)]);
+    }
+
+    #[test]
+    fn both_scans_agree() {
+        let (mut world, mut dispatcher) = indexed_world();
+        for i in 0..40u32 {
+            let coord = [(i * 7) % 13, (i * 5) % 11];
+            world.create_entity().with(GridPosition(coord)).build();
+        }
+        dispatcher.dispatch(&mut world.res);
+
+        let occupancy = world.read_resource::<Occupancy>();
+        for &(min, max) in &[([0, 0], [0, 0]), ([2, 3], [5, 4]), ([0, 0], [12, 10]), ([6, 1], [40, 2]), ([11, 9], [12, 10])] {
+            let rect = sorted(occupancy.scan_rect(min, max));
+            assert_eq!(rect, sorted(occupancy.scan_tiles(min, max)), "{:?} to {:?}", min, max);
+            assert_eq!(rect, sorted(occupancy.in_rect(min, max)));
+        }
+    }
+}
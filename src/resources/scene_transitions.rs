@@ -0,0 +1,49 @@
+
+use std::mem;
+
+use specs::{World, DispatcherBuilder};
+
+/// Every kind of scene the game can show.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SceneKind {
+    MainMenu,
+    Gameplay,
+    Pause,
+    LevelComplete,
+    GameOver,
+    Victory,
+}
+
+/// Change to the scene stack, applied after the current update.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Transition {
+    Push(SceneKind),
+    Pop,
+    Replace(SceneKind),
+    Quit,
+}
+
+/// Transitions requested by systems, collected by the scene owning the world.
+pub struct SceneTransitions(Vec<Transition>);
+
+impl SceneTransitions {
+    pub fn new() -> Self {
+        SceneTransitions(Vec::new())
+    }
+
+    pub fn request(&mut self, transition: Transition) {
+        self.0.push(transition);
+    }
+
+    /// Take all transitions in the order they were requested.
+    pub fn drain(&mut self) -> Vec<Transition> {
+        mem::replace(&mut self.0, Vec::new())
+    }
+}
+
+pub fn init_world<'a, 'b>(world: &mut World,
+                          dispatcher_builder: DispatcherBuilder<'a, 'b>)
+                          -> DispatcherBuilder<'a, 'b> {
+    world.add_resource(SceneTransitions::new());
+    dispatcher_builder
+}
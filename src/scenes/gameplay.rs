@@ -0,0 +1,341 @@
+
+use std::io::Write;
+use std::time::{Duration, Instant};
+
+use ggez::{GameError, GameResult, Context, graphics, timer};
+use ggez::graphics::{Point, Rect};
+use ggez::event::{Axis, Button, Keycode, MouseButton};
+
+use components::{animation, graphics as gfx, input, positioning, territory};
+use core::bindings::{Bindings, BINDINGS_PATH};
+use core::gesture::{TouchEvent, TouchPhase};
+use core::level::Level;
+use core::render_queue::{DrawCommand, Layer, RenderQueue};
+use core::replay::Replay;
+use core::tileset::TileSet;
+use resources::assets::Assets;
+use resources::screen_params;
+use resources::camera::Camera;
+use resources::clock::SimulationClock;
+use resources::level_progress::{LevelEvent, LevelProgress};
+use resources::scene_transitions::{SceneKind, SceneTransitions, Transition};
+use scenes::Scene;
+use simulation::Simulation;
+
+/// Levels in the order they are played.
+const LEVELS: &[&str] = &["/levels/01.lvl", "/levels/02.lvl"];
+
+/// Where the replay of the last session is stored in the user directory.
+const REPLAY_PATH: &str = "/last.replay";
+
+/// Sprite sheet the player and enemies are drawn from.
+const DUCK_ATLAS: &str = "/sprites/duck.atlas";
+
+/// Clips the player and enemies are animated with.
+const DUCK_CLIPS: &str = "/sprites/duck.anim";
+
+/// Sprites the board tiles are drawn with.
+const TILE_SET: &str = "/tiles/grass.tiles";
+
+/// Zoom factor applied per key press.
+const ZOOM_STEP: f32 = 1.25;
+
+pub struct Gameplay {
+    simulation: Simulation,
+    // Replay saving needs the context, so it waits for the next update
+    save_requested: bool,
+    // Reference point for touch timestamps, mouse events carry no time
+    created: Instant,
+    // Kept between frames to reuse its allocation
+    render_queue: RenderQueue<DrawCommand>,
+}
+
+impl Gameplay {
+    /// Start at the first level, recording the session.
+    pub fn new(ctx: &mut Context) -> GameResult<Gameplay> {
+        let mut gameplay = Gameplay::empty(ctx)?;
+        gameplay.load_level(ctx)?;
+
+        let level = gameplay.simulation.world.read_resource::<LevelProgress>().current_level().map(|l| l.to_string());
+        if let Some(level) = level {
+            gameplay.simulation.start_recording(&level);
+        }
+        Ok(gameplay)
+    }
+
+    /// Play a recorded session back instead of reading the keyboard.
+    ///
+    /// Nothing is recorded while playing back, so the replay of the last
+    /// session is left alone.
+    pub fn replay(ctx: &mut Context, replay: Replay) -> GameResult<Gameplay> {
+        let mut gameplay = Gameplay::empty(ctx)?;
+
+        gameplay.simulation.world.write_resource::<LevelProgress>().start_at(&replay.level);
+        gameplay.load_level(ctx)?;
+        gameplay.simulation.start_playback(replay);
+        Ok(gameplay)
+    }
+
+    /// Scene with its resources loaded but no level in the world yet.
+    fn empty(ctx: &mut Context) -> GameResult<Gameplay> {
+        let mut simulation = Simulation::build(|world, dispatcher_builder| {
+            let dispatcher_builder = gfx::init_world(world, dispatcher_builder);
+            animation::init_world(world, dispatcher_builder)
+        });
+
+        {
+            let world = &mut simulation.world;
+
+            let screen_rect = graphics::get_screen_coordinates(ctx);
+            world.write_resource::<Camera>().set_viewport(screen_rect.w, screen_rect.h.abs());
+
+            // Broken bindings should not keep anyone from playing
+            *world.write_resource::<Bindings>() = match Bindings::load(ctx, BINDINGS_PATH) {
+                Ok(bindings) => bindings,
+                Err(e) => {
+                    println!("Could not load the bindings, using the defaults: {}", e);
+                    Bindings::default()
+                }
+            };
+
+            let (font, tile_set) = {
+                let mut assets = world.write_resource::<Assets>();
+                (assets.load_font(ctx, "/fonts/Inter-UI-Regular.ttf", 12)?, TileSet::load(ctx, &mut assets, TILE_SET)?)
+            };
+            world.add_resource(gfx::RenderableFont(font));
+            *world.write_resource::<TileSet>() = tile_set;
+
+            let levels = LEVELS.iter().map(|l| l.to_string()).collect();
+            *world.write_resource::<LevelProgress>() = LevelProgress::new(levels);
+        }
+
+        Ok(Gameplay {
+            simulation: simulation,
+            save_requested: false,
+            created: Instant::now(),
+            render_queue: RenderQueue::new(),
+        })
+    }
+
+    /// Write the session recorded so far to the user directory, does nothing
+    /// during playback.
+    fn save_replay(&self, ctx: &mut Context) -> GameResult<()> {
+        if let Some(replay) = self.simulation.recording() {
+            let mut file = ctx.filesystem.create(REPLAY_PATH)?;
+            write!(file, "{}", replay)?;
+        }
+        Ok(())
+    }
+
+    fn touch(&mut self, phase: TouchPhase, x: i32, y: i32) {
+        let elapsed = self.created.elapsed();
+        let time = elapsed.as_secs() as f32 + elapsed.subsec_nanos() as f32 * 1e-9;
+        self.simulation.touch(TouchEvent::new(phase, x as f32, y as f32, time));
+    }
+
+    /// Load the current level of the progression and dress it up for drawing.
+    fn load_level(&mut self, ctx: &mut Context) -> GameResult<()> {
+        let path = match self.simulation.world.read_resource::<LevelProgress>().current_level() {
+            Some(path) => path.to_string(),
+            None => return Ok(()),
+        };
+
+        let level = Level::load(ctx, &path)?;
+        let spawned = self.simulation.load_level(level);
+        {
+            let tile_set = self.simulation.world.read_resource::<TileSet>();
+            tile_set.assign(&mut self.simulation.world.write_resource::<gfx::GameBoard>().0);
+        }
+
+        let (duck, clips) = {
+            let mut assets = self.simulation.world.write_resource::<Assets>();
+            let atlas = assets.load_atlas(ctx, DUCK_ATLAS)?;
+            let duck = match assets.atlas(atlas).region("duck") {
+                Some(duck) => duck,
+                None => return Err(GameError::ResourceLoadError(format!("{}: missing region `duck`", DUCK_ATLAS))),
+            };
+            (duck, assets.load_clips(ctx, DUCK_CLIPS)?)
+        };
+
+        // Entities without a direction, like bouncers, keep the still sprite
+        let mut sprites = self.simulation.world.write::<gfx::RenderableSprite>();
+        let mut animations = self.simulation.world.write::<animation::SpriteAnimation>();
+        for entity in Some(spawned.player).into_iter().chain(spawned.enemies) {
+            sprites.insert(entity, gfx::RenderableSprite(duck));
+            animations.insert(entity, animation::SpriteAnimation::new(clips));
+        }
+        Ok(())
+    }
+}
+
+impl Scene for Gameplay {
+    fn update(&mut self, ctx: &mut Context, dt: Duration) -> GameResult<Vec<Transition>> {
+
+        let playing_back = self.simulation.is_playing_back();
+        self.simulation.update(dt.as_secs() as f32 + dt.subsec_nanos() as f32 * 1e-9);
+
+        let mut transitions = self.simulation.world.write_resource::<SceneTransitions>().drain();
+
+        let events = self.simulation.world.write_resource::<LevelProgress>().drain_events();
+        for event in events {
+            match event {
+                LevelEvent::Complete(_) => transitions.push(Transition::Push(SceneKind::LevelComplete)),
+                LevelEvent::AllComplete => transitions = vec![Transition::Replace(SceneKind::Victory)],
+            }
+        }
+
+        if transitions.contains(&Transition::Push(SceneKind::LevelComplete)) {
+            self.load_level(ctx)?;
+        }
+
+        if self.save_requested ||
+           transitions.contains(&Transition::Replace(SceneKind::GameOver)) ||
+           transitions.contains(&Transition::Replace(SceneKind::Victory)) {
+            self.save_requested = false;
+            self.save_replay(ctx)?;
+        }
+
+        if playing_back && !self.simulation.is_playing_back() {
+            transitions.push(Transition::Replace(SceneKind::MainMenu));
+        }
+        Ok(transitions)
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
+        use specs::Join;
+
+        let entities = self.simulation.world.entities();
+        let positions = self.simulation.world.read::<positioning::Position>();
+        let previous_positions = self.simulation.world.read::<positioning::PreviousPosition>();
+        let grid_positions = self.simulation.world.read::<positioning::GridPosition>();
+        let sprites = self.simulation.world.read::<gfx::RenderableSprite>();
+        let trails = self.simulation.world.read::<territory::Trail>();
+        let controllables = self.simulation.world.read::<input::Controllable>();
+        let grid = self.simulation.world.write_resource::<gfx::GameBoard>();
+        let font = self.simulation.world.write_resource::<gfx::RenderableFont>();
+        let assets = self.simulation.world.read_resource::<Assets>();
+        let tile_set = self.simulation.world.read_resource::<TileSet>();
+        let camera = self.simulation.world.read_resource::<Camera>();
+        let alpha = self.simulation.world.read_resource::<SimulationClock>().alpha();
+
+        let queue = &mut self.render_queue;
+        grid.0.render(queue, &camera, &assets, &tile_set);
+        for trail in trails.join() {
+            grid.0.render_trail(queue, &camera, &trail.cells);
+        }
+
+        for (entity, position, sprite) in (&*entities, &positions, &sprites).join() {
+            // Blend between the last two ticks so movement stays smooth
+            let position = match previous_positions.get(entity) {
+                Some(previous) => previous.0 + (position.0 - previous.0) * alpha,
+                None => position.0,
+            };
+
+            // Stand the sprite on its position, images are drawn around their center
+            let zoom = camera.zoom();
+            let screen_pos = camera.world_to_screen(position);
+            let dest = Point::new(screen_pos.x, screen_pos.y - assets.sprite(sprite.0).height * zoom / 2.0);
+            queue.push(grid.0.depth_of(position),
+                       Layer::Entity,
+                       DrawCommand::Sprite {
+                           sprite: sprite.0,
+                           dest: dest,
+                           scale: zoom,
+                       });
+        }
+
+        queue.submit(ctx, &assets)?;
+
+        graphics::set_color(ctx, graphics::Color::new(1.0, 1.0, 1.0, 1.0))?;
+
+        for (_c, gp) in (&controllables, &grid_positions).join() {
+            let pos_string = format!("Grid: [{}, {}]", gp.0[0], gp.0[1]);
+            let pos_text = graphics::Text::new(ctx, pos_string.as_str(), assets.font(font.0)).unwrap();
+            let dest = graphics::Point::new((pos_text.width() / 2) as f32 + 15.0, (pos_text.height() / 2) as f32 + 36.0);
+            graphics::draw(ctx, &pos_text, dest, 0.0)?;
+        }
+
+        // TODO: Remove this bullshit, only for debugging
+        let fps = timer::get_fps(ctx);
+        let avg_delta = timer::get_average_delta(ctx);
+
+        // Put this mathz into a helper function, WHY is this not in std lib???
+        let avg_delta_u64 = avg_delta.as_secs() * 1000 + u64::from(avg_delta.subsec_nanos()) / 1_000_000;
+        let fps_string = format!("{:.1} fps ({} ms)", fps, avg_delta_u64);
+        let fps_text = graphics::Text::new(ctx, fps_string.as_str(), assets.font(font.0)).unwrap();
+        let dest = graphics::Point::new((fps_text.width() / 2) as f32 + 15.0, (fps_text.height() / 2) as f32 + 15.0);
+        graphics::draw(ctx, &fps_text, dest, 0.0)?;
+
+        Ok(())
+    }
+
+    fn key_down_event(&mut self, keycode: Keycode) {
+        if keycode == Keycode::Escape || keycode == Keycode::P {
+            self.simulation.world.write_resource::<SceneTransitions>().request(Transition::Push(SceneKind::Pause));
+            return;
+        }
+
+        if keycode == Keycode::F9 {
+            self.save_requested = true;
+            return;
+        }
+
+        if keycode == Keycode::Equals || keycode == Keycode::Minus {
+            let mut camera = self.simulation.world.write_resource::<Camera>();
+            let factor = if keycode == Keycode::Equals { ZOOM_STEP } else { 1.0 / ZOOM_STEP };
+            let zoom = camera.zoom() * factor;
+            camera.set_zoom(zoom);
+            return;
+        }
+
+        self.simulation.set_key(keycode, true);
+    }
+
+    fn key_up_event(&mut self, keycode: Keycode) {
+        self.simulation.set_key(keycode, false);
+    }
+
+    fn mouse_button_down_event(&mut self, button: MouseButton, x: i32, y: i32) {
+        if button == MouseButton::Left {
+            self.touch(TouchPhase::Began, x, y);
+        }
+    }
+
+    fn mouse_button_up_event(&mut self, button: MouseButton, x: i32, y: i32) {
+        if button == MouseButton::Left {
+            self.touch(TouchPhase::Ended, x, y);
+        }
+    }
+
+    fn mouse_motion_event(&mut self, x: i32, y: i32) {
+        self.touch(TouchPhase::Moved, x, y);
+    }
+
+    fn controller_button_down_event(&mut self, btn: Button, instance_id: i32) {
+        if btn == Button::Start {
+            self.simulation.world.write_resource::<SceneTransitions>().request(Transition::Push(SceneKind::Pause));
+            return;
+        }
+
+        self.simulation.set_button(instance_id, btn, true);
+    }
+
+    fn controller_button_up_event(&mut self, btn: Button, instance_id: i32) {
+        self.simulation.set_button(instance_id, btn, false);
+    }
+
+    fn controller_axis_event(&mut self, axis: Axis, value: i16, instance_id: i32) {
+        self.simulation.set_axis(instance_id, axis, value);
+    }
+
+    fn focus_event(&mut self, gained: bool) {
+        self.simulation.world.write_resource::<SimulationClock>().set_paused(!gained);
+    }
+
+    fn resize_event(&mut self, _ctx: &mut Context, width: u32, height: u32) {
+        self.simulation.world.write_resource::<Camera>().set_viewport(width as f32, height as f32);
+
+        let mut params = self.simulation.world.write_resource::<screen_params::ScreenParams>();
+        *params = screen_params::ScreenParams(Rect::new(0.0, width as f32, 0.0, height as f32));
+    }
+}
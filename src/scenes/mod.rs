@@ -0,0 +1,212 @@
+
+pub mod gameplay;
+pub mod screens;
+
+use std::time::Duration;
+
+use ggez::{GameResult, Context, graphics, timer};
+use ggez::event::{Axis, Button, EventHandler, Keycode, Mod, MouseButton, MouseState};
+
+use resources::assets::Assets;
+use resources::scene_transitions::{SceneKind, Transition};
+
+/// A screen of the game, only the scene on top of the stack is updated and
+/// receives input.
+pub trait Scene {
+    fn update(&mut self, ctx: &mut Context, dt: Duration) -> GameResult<Vec<Transition>>;
+
+    fn draw(&mut self, ctx: &mut Context) -> GameResult<()>;
+
+    fn key_down_event(&mut self, _keycode: Keycode) {}
+
+    fn key_up_event(&mut self, _keycode: Keycode) {}
+
+    fn mouse_button_down_event(&mut self, _button: MouseButton, _x: i32, _y: i32) {}
+
+    fn mouse_button_up_event(&mut self, _button: MouseButton, _x: i32, _y: i32) {}
+
+    fn mouse_motion_event(&mut self, _x: i32, _y: i32) {}
+
+    fn controller_button_down_event(&mut self, _btn: Button, _instance_id: i32) {}
+
+    fn controller_button_up_event(&mut self, _btn: Button, _instance_id: i32) {}
+
+    fn controller_axis_event(&mut self, _axis: Axis, _value: i16, _instance_id: i32) {}
+
+    fn resize_event(&mut self, _ctx: &mut Context, _width: u32, _height: u32) {}
+
+    fn focus_event(&mut self, _gained: bool) {}
+
+    /// Overlays are drawn on top of the scene below them.
+    fn is_overlay(&self) -> bool {
+        false
+    }
+}
+
+/// Create a fresh scene of a kind, static screens load their fonts into the
+/// shared assets.
+pub fn create_scene(ctx: &mut Context, assets: &mut Assets, kind: SceneKind) -> GameResult<Box<Scene>> {
+    let scene: Box<Scene> = match kind {
+        SceneKind::Gameplay => Box::new(gameplay::Gameplay::new(ctx)?),
+        _ => Box::new(screens::Screen::new(ctx, assets, kind)?),
+    };
+    Ok(scene)
+}
+
+pub struct SceneStack {
+    scenes: Vec<Box<Scene>>,
+    /// Assets of the static screens, kept so fonts load once for all of them.
+    assets: Assets,
+}
+
+impl SceneStack {
+    pub fn new(ctx: &mut Context, initial: SceneKind) -> GameResult<Self> {
+        let mut stack = SceneStack {
+            scenes: Vec::new(),
+            assets: Assets::new(),
+        };
+        let scene = create_scene(ctx, &mut stack.assets, initial)?;
+        stack.push(scene);
+        Ok(stack)
+    }
+
+    /// Start with an already created scene.
+    pub fn from_scene(scene: Box<Scene>) -> Self {
+        SceneStack {
+            scenes: vec![scene],
+            assets: Assets::new(),
+        }
+    }
+
+    pub fn push(&mut self, scene: Box<Scene>) {
+        self.scenes.push(scene);
+    }
+
+    pub fn pop(&mut self) -> Option<Box<Scene>> {
+        self.scenes.pop()
+    }
+
+    /// Swap the top scene for another one, returning the old scene.
+    pub fn replace(&mut self, scene: Box<Scene>) -> Option<Box<Scene>> {
+        let old = self.scenes.pop();
+        self.scenes.push(scene);
+        old
+    }
+
+    fn apply(&mut self, ctx: &mut Context, transition: Transition) -> GameResult<()> {
+        match transition {
+            Transition::Push(kind) => {
+                let scene = create_scene(ctx, &mut self.assets, kind)?;
+                self.push(scene);
+            }
+            Transition::Pop => {
+                self.pop();
+            }
+            Transition::Replace(kind) => {
+                let scene = create_scene(ctx, &mut self.assets, kind)?;
+                self.replace(scene);
+            }
+            Transition::Quit => self.scenes.clear(),
+        }
+        Ok(())
+    }
+}
+
+impl EventHandler for SceneStack {
+    fn update(&mut self, ctx: &mut Context, dt: Duration) -> GameResult<()> {
+        let transitions = match self.scenes.last_mut() {
+            Some(scene) => scene.update(ctx, dt)?,
+            None => Vec::new(),
+        };
+
+        for transition in transitions {
+            self.apply(ctx, transition)?;
+        }
+
+        if self.scenes.is_empty() {
+            ctx.quit()?;
+        }
+        Ok(())
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
+        graphics::clear(ctx);
+
+        // Start from the topmost scene that covers the whole screen
+        let first = self.scenes.iter().rposition(|s| !s.is_overlay()).unwrap_or(0);
+        for scene in &mut self.scenes[first..] {
+            scene.draw(ctx)?;
+        }
+
+        graphics::present(ctx);
+
+        // Keep for prototyping, do actual signaling to the OS for real product
+        timer::sleep(Duration::from_secs(0));
+        Ok(())
+    }
+
+    fn key_down_event(&mut self, keycode: Keycode, _keymod: Mod, _repeat: bool) {
+        if let Some(scene) = self.scenes.last_mut() {
+            scene.key_down_event(keycode);
+        }
+    }
+
+    fn key_up_event(&mut self, keycode: Keycode, _keymod: Mod, _repeat: bool) {
+        // Releases go everywhere so no key is stuck once an overlay is closed
+        for scene in &mut self.scenes {
+            scene.key_up_event(keycode);
+        }
+    }
+
+    // SDL reports touches as mouse events as well, so these cover both
+    fn mouse_button_down_event(&mut self, button: MouseButton, x: i32, y: i32) {
+        if let Some(scene) = self.scenes.last_mut() {
+            scene.mouse_button_down_event(button, x, y);
+        }
+    }
+
+    fn mouse_button_up_event(&mut self, button: MouseButton, x: i32, y: i32) {
+        for scene in &mut self.scenes {
+            scene.mouse_button_up_event(button, x, y);
+        }
+    }
+
+    fn mouse_motion_event(&mut self, _state: MouseState, x: i32, y: i32, _xrel: i32, _yrel: i32) {
+        if let Some(scene) = self.scenes.last_mut() {
+            scene.mouse_motion_event(x, y);
+        }
+    }
+
+    fn controller_button_down_event(&mut self, btn: Button, instance_id: i32) {
+        if let Some(scene) = self.scenes.last_mut() {
+            scene.controller_button_down_event(btn, instance_id);
+        }
+    }
+
+    fn controller_button_up_event(&mut self, btn: Button, instance_id: i32) {
+        for scene in &mut self.scenes {
+            scene.controller_button_up_event(btn, instance_id);
+        }
+    }
+
+    fn controller_axis_event(&mut self, axis: Axis, value: i16, instance_id: i32) {
+        // Sticks return to rest everywhere, like key releases
+        for scene in &mut self.scenes {
+            scene.controller_axis_event(axis, value, instance_id);
+        }
+    }
+
+    fn resize_event(&mut self, ctx: &mut Context, width: u32, height: u32) {
+        graphics::set_screen_coordinates(ctx, 0.0, width as f32, 0.0, height as f32).unwrap();
+
+        for scene in &mut self.scenes {
+            scene.resize_event(ctx, width, height);
+        }
+    }
+
+    fn focus_event(&mut self, gained: bool) {
+        for scene in &mut self.scenes {
+            scene.focus_event(gained);
+        }
+    }
+}
@@ -0,0 +1,88 @@
+
+use std::time::Duration;
+
+use ggez::{GameResult, Context, graphics};
+use ggez::graphics::{Point, Text};
+use ggez::event::{Button, Keycode};
+
+use resources::assets::Assets;
+use resources::scene_transitions::{SceneKind, Transition};
+use scenes::Scene;
+
+/// Static screen with a title and a hint, left by pressing a key.
+pub struct Screen {
+    kind: SceneKind,
+    title: Text,
+    hint: Text,
+    transitions: Vec<Transition>,
+}
+
+impl Screen {
+    pub fn new(ctx: &mut Context, assets: &mut Assets, kind: SceneKind) -> GameResult<Screen> {
+        let (title, hint) = match kind {
+            SceneKind::MainMenu => ("ROBOFILL", "Press Enter to start, Escape to quit"),
+            SceneKind::Pause => ("Paused", "Press P to continue"),
+            SceneKind::LevelComplete => ("Level complete", "Press Enter to continue"),
+            SceneKind::GameOver => ("Game over", "Press Enter to return to the menu"),
+            SceneKind::Victory => ("All levels cleared", "Press Enter to return to the menu"),
+            SceneKind::Gameplay => panic!("gameplay is not a static screen"),
+        };
+
+        let title_font = assets.load_font(ctx, "/fonts/Inter-UI-Regular.ttf", 32)?;
+        let hint_font = assets.load_font(ctx, "/fonts/Inter-UI-Regular.ttf", 14)?;
+
+        Ok(Screen {
+            kind: kind,
+            title: Text::new(ctx, title, assets.font(title_font))?,
+            hint: Text::new(ctx, hint, assets.font(hint_font))?,
+            transitions: Vec::new(),
+        })
+    }
+}
+
+impl Scene for Screen {
+    fn update(&mut self, _ctx: &mut Context, _dt: Duration) -> GameResult<Vec<Transition>> {
+        Ok(self.transitions.drain(..).collect())
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
+        let screen = graphics::get_screen_coordinates(ctx);
+        let center_x = screen.w / 2.0;
+        let center_y = screen.h.abs() / 2.0;
+
+        graphics::set_color(ctx, graphics::Color::new(1.0, 1.0, 1.0, 1.0))?;
+        graphics::draw(ctx, &self.title, Point::new(center_x, center_y - 20.0), 0.0)?;
+        graphics::draw(ctx, &self.hint, Point::new(center_x, center_y + 20.0), 0.0)?;
+        Ok(())
+    }
+
+    fn key_down_event(&mut self, keycode: Keycode) {
+        let transition = match (self.kind, keycode) {
+            (SceneKind::MainMenu, Keycode::Return) => Transition::Replace(SceneKind::Gameplay),
+            (SceneKind::MainMenu, Keycode::Escape) => Transition::Quit,
+            (SceneKind::Pause, Keycode::P) |
+            (SceneKind::Pause, Keycode::Escape) |
+            (SceneKind::LevelComplete, Keycode::Return) => Transition::Pop,
+            (SceneKind::GameOver, Keycode::Return) |
+            (SceneKind::Victory, Keycode::Return) => Transition::Replace(SceneKind::MainMenu),
+            _ => return,
+        };
+
+        self.transitions.push(transition);
+    }
+
+    fn controller_button_down_event(&mut self, btn: Button, _instance_id: i32) {
+        // Pads navigate the screens like the keys they stand in for
+        let keycode = match (self.kind, btn) {
+            (SceneKind::Pause, Button::Start) => Keycode::P,
+            (_, Button::A) | (_, Button::Start) => Keycode::Return,
+            (_, Button::B) | (_, Button::Back) => Keycode::Escape,
+            _ => return,
+        };
+        self.key_down_event(keycode);
+    }
+
+    fn is_overlay(&self) -> bool {
+        self.kind == SceneKind::Pause || self.kind == SceneKind::LevelComplete
+    }
+}
@@ -0,0 +1,340 @@
+
+use cgmath::Vector2;
+use ggez::event::{Axis, Button, Keycode};
+use ggez::graphics::Point;
+use specs::{World, Dispatcher, DispatcherBuilder, Entity};
+
+use components::{collision, enemy, input, navigation, positioning, territory};
+use components::graphics::GameBoard;
+use core::bindings::Bindings;
+use core::gesture::{Gesture, TouchEvent};
+use core::grid::{GridCoordinate, GridDirection};
+use core::level::{EnemyKind, Level};
+use core::replay::{Replay, ReplayFrame};
+use core::rng::Rng;
+use core::tween::Easing;
+use resources::{camera, clock, delta_time, level_progress, occupancy, scene_transitions, screen_params};
+use resources::clock::SimulationClock;
+use resources::camera::{Camera, CameraTarget};
+use resources::level_progress::LevelProgress;
+
+/// Seconds the player takes to step onto the next tile.
+const PLAYER_STEP: f32 = 0.125;
+
+/// Seconds a patroller takes to step onto the next tile.
+const PATROLLER_STEP: f32 = 0.25;
+
+/// Entities created for a level, so callers can decorate them.
+pub struct SpawnedLevel {
+    pub player: Entity,
+    pub enemies: Vec<Entity>,
+}
+
+/// Game logic without a window.
+///
+/// Owns the ECS world and every gameplay system, but nothing that needs a
+/// `ggez::Context`. The gameplay scene renders on top of it, tests step it
+/// directly with synthetic input.
+pub struct Simulation {
+    pub world: World,
+    dispatcher: Dispatcher<'static, 'static>,
+    recording: Option<Replay>,
+    // Replay replacing the live keyboard and the next frame to play
+    playback: Option<(Replay, usize)>,
+}
+
+impl Simulation {
+    /// Build the world with only the gameplay systems.
+    pub fn new() -> Self {
+        Simulation::build(|_world, dispatcher_builder| dispatcher_builder)
+    }
+
+    /// Build the world with the gameplay systems, then let `extra` register
+    /// anything on top, like rendering components.
+    pub fn build<F>(extra: F) -> Self
+        where F: FnOnce(&mut World, DispatcherBuilder<'static, 'static>) -> DispatcherBuilder<'static, 'static>
+    {
+        // ECS world type
+        let mut world = World::new();
+        let mut dispatcher_builder = DispatcherBuilder::new();
+
+        dispatcher_builder = delta_time::init_world(&mut world, dispatcher_builder);
+        dispatcher_builder = clock::init_world(&mut world, dispatcher_builder);
+        dispatcher_builder = screen_params::init_world(&mut world, dispatcher_builder);
+        dispatcher_builder = scene_transitions::init_world(&mut world, dispatcher_builder);
+        dispatcher_builder = positioning::init_world(&mut world, dispatcher_builder);
+        dispatcher_builder = collision::init_world(&mut world, dispatcher_builder);
+        dispatcher_builder = input::init_world(&mut world, dispatcher_builder);
+        dispatcher_builder = navigation::init_world(&mut world, dispatcher_builder);
+        dispatcher_builder = enemy::init_world(&mut world, dispatcher_builder);
+        dispatcher_builder = occupancy::init_world(&mut world, dispatcher_builder);
+        dispatcher_builder = territory::init_world(&mut world, dispatcher_builder);
+        dispatcher_builder = level_progress::init_world(&mut world, dispatcher_builder);
+        dispatcher_builder = camera::init_world(&mut world, dispatcher_builder);
+        dispatcher_builder = extra(&mut world, dispatcher_builder);
+
+        Simulation {
+            world: world,
+            dispatcher: dispatcher_builder.build(),
+            recording: None,
+            playback: None,
+        }
+    }
+
+    /// Replace everything in the world with a level.
+    pub fn load_level(&mut self, level: Level) -> SpawnedLevel {
+        use specs::Join;
+
+        {
+            let entities = self.world.entities();
+            for entity in (&*entities).join() {
+                let _ = entities.delete(entity);
+            }
+        }
+        self.world.maintain();
+
+        spawn_level(&mut self.world, level)
+    }
+
+    /// Add the time of a frame and run as many fixed ticks as fit, returns
+    /// the number of ticks run.
+    pub fn update(&mut self, frame_delta: f32) -> u32 {
+        let (ticks, tick) = {
+            let mut clock = self.world.write_resource::<SimulationClock>();
+            (clock.advance(frame_delta), clock.tick())
+        };
+
+        for _ in 0..ticks {
+            self.step(tick);
+        }
+        ticks
+    }
+
+    /// Run every system once with a time step in seconds.
+    ///
+    /// While a replay is played back the recorded time step and input are used
+    /// instead.
+    pub fn step(&mut self, delta: f32) {
+        let mut delta = delta;
+
+        self.world.write_resource::<input::TouchInput>().start_step();
+
+        if let Some((ref replay, ref mut next)) = self.playback {
+            if let Some(frame) = replay.frames.get(*next) {
+                let mut keyboard_input = self.world.write_resource::<input::KeyboardInput>();
+                keyboard_input.clear();
+                // Keys are stored oldest press first, pressing them in order
+                // restores which came last
+                for &key in &frame.pressed {
+                    keyboard_input.set(key, true);
+                }
+
+                // Recorded gamepad state is already merged, replay it as one pad
+                let mut gamepad_input = self.world.write_resource::<input::GamepadInput>();
+                gamepad_input.clear();
+                for &btn in &frame.buttons {
+                    gamepad_input.set_button(0, btn, true);
+                }
+                for &(axis, value) in &frame.axes {
+                    gamepad_input.set_axis_value(0, axis, value);
+                }
+
+                self.world.write_resource::<input::TouchInput>().set_active(frame.touches.clone());
+                delta = frame.delta;
+                *next += 1;
+            }
+        }
+
+        if let Some(ref mut replay) = self.recording {
+            // Press order decides the direction, so keys are kept in it
+            let pressed = self.world.read_resource::<input::KeyboardInput>().pressed_keys();
+
+            // Sorted so equal sessions give equal files
+            let gamepad_input = self.world.read_resource::<input::GamepadInput>();
+            let mut buttons = gamepad_input.pressed_buttons();
+            buttons.sort_by_key(|btn| btn.string());
+            let mut axes = gamepad_input.moved_axes();
+            axes.sort_by_key(|&(axis, _)| axis.string());
+            let touches = self.world.read_resource::<input::TouchInput>().active().to_vec();
+
+            replay.frames.push(ReplayFrame {
+                delta: delta,
+                pressed: pressed,
+                buttons: buttons,
+                axes: axes,
+                touches: touches,
+            });
+        }
+
+        *self.world.write_resource::<delta_time::DeltaTime>() = delta_time::DeltaTime(delta);
+        self.dispatcher.dispatch(&mut self.world.res);
+    }
+
+    /// Press or release a key as if it came from the keyboard, ignored while
+    /// a replay is played back.
+    pub fn set_key(&mut self, keycode: Keycode, pressed: bool) {
+        if self.playback.is_some() {
+            return;
+        }
+        self.world.write_resource::<input::KeyboardInput>().set(keycode, pressed);
+    }
+
+    /// Press or release a controller button, ignored during playback.
+    pub fn set_button(&mut self, instance_id: i32, btn: Button, pressed: bool) {
+        if self.playback.is_some() {
+            return;
+        }
+        self.world.write_resource::<input::GamepadInput>().set_button(instance_id, btn, pressed);
+    }
+
+    /// Move a controller axis to a raw SDL value, ignored during playback.
+    pub fn set_axis(&mut self, instance_id: i32, axis: Axis, value: i16) {
+        if self.playback.is_some() {
+            return;
+        }
+        self.world.write_resource::<input::GamepadInput>().set_axis(instance_id, axis, value);
+    }
+
+    /// Feed a touch event, swipes turn into actions and taps into a tile to
+    /// walk to. Ignored during playback.
+    pub fn touch(&mut self, event: TouchEvent) {
+        if self.playback.is_some() {
+            return;
+        }
+
+        let gesture = self.world.write_resource::<input::TouchInput>().recognizer.handle(event);
+        let command = match gesture {
+            Some(Gesture::Swipe(offset)) => Some(input::TouchCommand::Action(input::swipe_action(offset))),
+            Some(Gesture::Tap(point)) => self.tap_target(point).map(input::TouchCommand::Goto),
+            None => None,
+        };
+
+        if let Some(command) = command {
+            self.world.write_resource::<input::TouchInput>().push(command);
+        }
+    }
+
+    /// Tile under a screen point.
+    fn tap_target(&self, point: Vector2<f32>) -> Option<GridCoordinate> {
+        let board = self.world.read_resource::<GameBoard>();
+        let camera = self.world.read_resource::<Camera>();
+        board.0.tile_at_screen(Point::new(point.x, point.y), &camera)
+    }
+
+    /// Start recording every step, starting on the given level with the
+    /// current bindings.
+    pub fn start_recording(&mut self, level: &str) {
+        let bindings = self.world.read_resource::<Bindings>().clone();
+        self.recording = Some(Replay::new(level, bindings));
+    }
+
+    /// Everything recorded so far.
+    pub fn recording(&self) -> Option<&Replay> {
+        self.recording.as_ref()
+    }
+
+    /// Drive the simulation from a replay instead of the live keyboard.
+    ///
+    /// The bindings stored with the replay replace the player's, so the
+    /// recorded input triggers the same actions it did.
+    pub fn start_playback(&mut self, replay: Replay) {
+        use specs::Join;
+
+        *self.world.write_resource::<Bindings>() = replay.bindings.clone();
+        {
+            let mut mappings = self.world.write::<input::InputMapping>();
+            for mapping in (&mut mappings).join() {
+                *mapping = input::InputMapping::from_bindings(&replay.bindings);
+            }
+        }
+        self.playback = Some((replay, 0));
+    }
+
+    /// Check if a replay is played back and still has frames left.
+    pub fn is_playing_back(&self) -> bool {
+        match self.playback {
+            Some((ref replay, next)) => next < replay.frames.len(),
+            None => false,
+        }
+    }
+}
+
+/// Add the board of a level to the world and create the player and enemies.
+fn spawn_level(world: &mut World, level: Level) -> SpawnedLevel {
+    let seed = level.seed.unwrap_or(enemy::DEFAULT_SEED);
+    *world.write_resource::<enemy::EnemyRng>() = enemy::EnemyRng(Rng::new(seed));
+    world.write_resource::<LevelProgress>().start_level(level.target_fill);
+    world.write_resource::<occupancy::Occupancy>().clear();
+
+    let board = level.board;
+
+    let mapping = input::InputMapping::from_bindings(&world.read_resource::<Bindings>());
+
+    // Player entity
+    let spawn = level.player_spawn;
+    let player = world.create_entity()
+        .with(positioning::Position(board.get_tile_center_world_coordinate(spawn)))
+        .with(positioning::GridPosition(spawn))
+        .with(positioning::StepMotion::new(PLAYER_STEP, Easing::Linear))
+        .with(positioning::TargetPosition(None))
+        .with(positioning::Animating(false))
+        .with(positioning::Direction(GridDirection::DirectionSouth))
+        .with(input::Controllable)
+        .with(input::InputBuffer::new(input::DEFAULT_BUFFER_EXPIRY))
+        .with(navigation::PathFollow::new())
+        .with(CameraTarget)
+        .with(territory::Trail::new(spawn))
+        .with(mapping)
+        .build();
+
+    let mut enemies = Vec::new();
+    for spawn in &level.enemy_spawns {
+        let position = board.get_tile_center_world_coordinate(spawn.position);
+
+        let entity = match spawn.kind {
+            EnemyKind::Bouncer => {
+                let velocity = {
+                    let mut rng = world.write_resource::<enemy::EnemyRng>();
+                    enemy::Bouncer::initial_velocity(&mut rng.0, 60.0)
+                };
+
+                world.create_entity()
+                    .with(positioning::Position(position))
+                    .with(positioning::GridPosition(spawn.position))
+                    .with(positioning::Velocity(velocity))
+                    .with(enemy::Enemy)
+                    .with(enemy::Bouncer)
+                    .build()
+            }
+            EnemyKind::Patroller => {
+                world.create_entity()
+                    .with(positioning::Position(position))
+                    .with(positioning::GridPosition(spawn.position))
+                    .with(positioning::StepMotion::new(PATROLLER_STEP, Easing::QuadInOut))
+                    .with(positioning::TargetPosition(None))
+                    .with(positioning::Animating(false))
+                    .with(positioning::Direction(GridDirection::DirectionNorth))
+                    .with(collision::Collider::new(collision::Walkability::SafeOnly))
+                    .with(collision::Solid)
+                    .with(enemy::Enemy)
+                    .with(enemy::Patroller::new())
+                    .build()
+            }
+        };
+        enemies.push(entity);
+    }
+
+    {
+        let mut camera = world.write_resource::<Camera>();
+        let (min, max) = board.iso_bounds();
+        camera.set_bounds(min, max);
+        camera.look_at(board.get_tile_center_world_coordinate(level.player_spawn));
+    }
+
+    *world.write_resource::<GameBoard>() = GameBoard(board);
+
+    SpawnedLevel {
+        player: player,
+        enemies: enemies,
+    }
+}
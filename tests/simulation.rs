@@ -0,0 +1,216 @@
+extern crate cgmath;
+extern crate ggez;
+extern crate robofill;
+extern crate specs;
+
+use ggez::event::Keycode;
+use cgmath::Vector2;
+use specs::Entity;
+
+use robofill::components::graphics::GameBoard;
+use robofill::components::positioning::{GridPosition, Position, Velocity};
+use robofill::components::territory::{Lives, Trail};
+use robofill::core::grid::GridCoordinate;
+use robofill::core::level::Level;
+use robofill::resources::scene_transitions::{SceneKind, SceneTransitions, Transition};
+use robofill::simulation::Simulation;
+
+const STEP: f32 = 1.0 / 60.0;
+
+const MEADOW: &str = include_str!("../resources/levels/01.lvl");
+
+const FIELD: &str = "player = 1 1
+
+[tiles]
+########
+#......#
+#......#
+#......#
+########
+";
+
+fn grid_position(simulation: &Simulation, entity: Entity) -> GridCoordinate {
+    simulation.world.read::<GridPosition>().get(entity).unwrap().0
+}
+
+#[test]
+fn player_stays_without_input() {
+    let mut simulation = Simulation::new();
+    let spawned = simulation.load_level(Level::parse(FIELD).unwrap());
+
+    for _ in 0..60 {
+        simulation.step(STEP);
+    }
+    assert_eq!(grid_position(&simulation, spawned.player), [1, 1]);
+}
+
+#[test]
+fn held_key_walks_the_player() {
+    let mut simulation = Simulation::new();
+    let spawned = simulation.load_level(Level::parse(FIELD).unwrap());
+
+    simulation.set_key(Keycode::D, true);
+    for _ in 0..30 {
+        simulation.step(STEP);
+    }
+    simulation.set_key(Keycode::D, false);
+    for _ in 0..30 {
+        simulation.step(STEP);
+    }
+
+    let position = grid_position(&simulation, spawned.player);
+    assert!(position[0] > 1, "player did not move right: {:?}", position);
+    assert_eq!(position[1], 1);
+}
+
+/// Positions of every given entity, in order.
+fn positions(simulation: &Simulation, entities: &[Entity]) -> Vec<(f32, f32)> {
+    let positions = simulation.world.read::<Position>();
+    entities.iter()
+        .map(|&e| {
+            let p = positions.get(e).unwrap().0;
+            (p.x, p.y)
+        })
+        .collect()
+}
+
+#[test]
+fn replay_reaches_the_recorded_state() {
+    let mut recorded = Simulation::new();
+    let spawned = recorded.load_level(Level::parse(MEADOW).unwrap());
+    recorded.start_recording("/levels/01.lvl");
+
+    for &(key, frames) in &[(Keycode::D, 40), (Keycode::S, 50), (Keycode::A, 20)] {
+        recorded.set_key(key, true);
+        for _ in 0..frames {
+            recorded.step(STEP);
+        }
+        recorded.set_key(key, false);
+    }
+    for _ in 0..30 {
+        recorded.step(STEP);
+    }
+
+    let replay = recorded.recording().unwrap().clone();
+    assert_eq!(replay.frames.len(), 140);
+
+    let mut played = Simulation::new();
+    let respawned = played.load_level(Level::parse(MEADOW).unwrap());
+    played.start_playback(replay);
+    while played.is_playing_back() {
+        // The recorded time step wins over this one
+        played.step(1.0);
+    }
+
+    let mut before = vec![spawned.player];
+    before.extend(spawned.enemies.iter().cloned());
+    let mut after = vec![respawned.player];
+    after.extend(respawned.enemies.iter().cloned());
+
+    assert_eq!(grid_position(&played, respawned.player), grid_position(&recorded, spawned.player));
+    assert_eq!(positions(&played, &after), positions(&recorded, &before));
+    assert!(played.world.read_resource::<GameBoard>().0 == recorded.world.read_resource::<GameBoard>().0);
+}
+
+/// Open field the player starts next to, with a bouncer waiting in a corner.
+const GUARDED_FIELD: &str = "player = 1 0
+bouncer = 6 4
+
+[tiles]
+########
+#......#
+#......#
+#......#
+#......#
+########
+";
+
+/// Hold a key until the player stands on a tile matching `done`.
+fn walk<F>(simulation: &mut Simulation, player: Entity, key: Keycode, done: F)
+    where F: Fn(GridCoordinate) -> bool
+{
+    simulation.set_key(key, true);
+    for _ in 0..600 {
+        if done(grid_position(simulation, player)) {
+            break;
+        }
+        simulation.step(STEP);
+    }
+    simulation.set_key(key, false);
+    assert!(done(grid_position(simulation, player)), "player stuck at {:?}", grid_position(simulation, player));
+}
+
+fn trail(simulation: &Simulation, player: Entity) -> Vec<GridCoordinate> {
+    simulation.world.read::<Trail>().get(player).unwrap().cells.clone()
+}
+
+/// Walk the player two tiles into the open, then drop the bouncer onto the
+/// first tile of the trail and let it stand there for one step.
+fn cut_trail(simulation: &mut Simulation) -> Entity {
+    let spawned = simulation.load_level(Level::parse(GUARDED_FIELD).unwrap());
+    let player = spawned.player;
+    walk(simulation, player, Keycode::S, |p| p == [1, 2]);
+    assert_eq!(&trail(simulation, player)[..2], &[[1, 1], [1, 2]]);
+
+    let bouncer = spawned.enemies[0];
+    let center = simulation.world.read_resource::<GameBoard>().0.get_tile_center_world_coordinate([1, 1]);
+    simulation.world.write::<Position>().get_mut(bouncer).unwrap().0 = center;
+    simulation.world.write::<GridPosition>().get_mut(bouncer).unwrap().0 = [1, 1];
+    simulation.world.write::<Velocity>().get_mut(bouncer).unwrap().0 = Vector2::new(0.0, 0.0);
+    simulation.step(STEP);
+    player
+}
+
+#[test]
+fn trail_grows_on_open_ground_and_clears_on_safe_ground() {
+    let mut simulation = Simulation::new();
+    let spawned = simulation.load_level(Level::parse(&GUARDED_FIELD.replace("bouncer = 6 4\n", "")).unwrap());
+    let player = spawned.player;
+
+    walk(&mut simulation, player, Keycode::D, |p| p[0] == 3);
+    // Let a step started while the key was held finish
+    for _ in 0..30 {
+        simulation.step(STEP);
+    }
+    let x = grid_position(&simulation, player)[0];
+    assert!(trail(&simulation, player).is_empty(), "safe ground left a trail");
+
+    walk(&mut simulation, player, Keycode::S, |p| p[1] == 2);
+    let cells = trail(&simulation, player);
+    assert_eq!(&cells[..2], &[[x, 1], [x, 2]]);
+    {
+        let board = simulation.world.read_resource::<GameBoard>();
+        assert!(cells.iter().all(|&c| !board.0.is_free_move(c)), "{:?}", cells);
+    }
+
+    walk(&mut simulation, player, Keycode::S, |p| p[1] == 5);
+    let home = grid_position(&simulation, player);
+    assert!(trail(&simulation, player).is_empty());
+    assert_eq!(simulation.world.read::<Trail>().get(player).unwrap().safe_tile, home);
+    // Without enemies both halves of the field are captured
+    assert_eq!(simulation.world.read_resource::<GameBoard>().0.fill_percentage(), 100.0);
+}
+
+#[test]
+fn enemy_on_the_trail_costs_a_life() {
+    let mut simulation = Simulation::new();
+    let player = cut_trail(&mut simulation);
+
+    assert_eq!(simulation.world.read_resource::<Lives>().0, 2);
+    assert!(trail(&simulation, player).is_empty());
+    assert_eq!(grid_position(&simulation, player), [1, 0]);
+    let expected = simulation.world.read_resource::<GameBoard>().0.get_tile_center_world_coordinate([1, 0]);
+    assert_eq!(simulation.world.read::<Position>().get(player).unwrap().0, expected);
+    assert!(simulation.world.write_resource::<SceneTransitions>().drain().is_empty());
+}
+
+#[test]
+fn last_life_ends_the_game() {
+    let mut simulation = Simulation::new();
+    simulation.world.write_resource::<Lives>().0 = 1;
+    cut_trail(&mut simulation);
+
+    assert_eq!(simulation.world.read_resource::<Lives>().0, 0);
+    assert_eq!(simulation.world.write_resource::<SceneTransitions>().drain(),
+               vec![Transition::Replace(SceneKind::GameOver)]);
+}